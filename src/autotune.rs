@@ -0,0 +1,187 @@
+//! Automatic resizing of a [RotatingBuffer] based on its observed occupancy.
+
+use std::fmt;
+
+use crate::RotatingBuffer;
+
+/// A resize made by an [AutoTuner], passed to its callback and returned from
+/// [AutoTuner::observe].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResizeDecision {
+    /// The capacity before resizing.
+    pub from: usize,
+    /// The capacity after resizing.
+    pub to: usize,
+    /// The highest occupancy observed during the window that led to the decision.
+    pub peak: usize,
+    /// How many overflows were recorded during that window.
+    pub overflows: usize,
+}
+
+/// The [AutoTuner] samples the occupancy of a [RotatingBuffer] and resizes it, within
+/// configured bounds, to match the observed load.
+///
+/// Every `window` calls to [AutoTuner::observe], the ring is doubled if it filled up or an
+/// overflow was recorded with [AutoTuner::record_overflow], and halved (but kept at least
+/// twice the peak occupancy) if it never got more than a quarter full.
+pub struct AutoTuner {
+    min_capacity: usize,
+    max_capacity: usize,
+    window: usize,
+    samples: usize,
+    peak: usize,
+    overflows: usize,
+    on_resize: Option<Box<dyn FnMut(ResizeDecision) + Send>>,
+}
+
+impl fmt::Debug for AutoTuner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AutoTuner")
+            .field("min_capacity", &self.min_capacity)
+            .field("max_capacity", &self.max_capacity)
+            .field("window", &self.window)
+            .field("samples", &self.samples)
+            .field("peak", &self.peak)
+            .field("overflows", &self.overflows)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AutoTuner {
+    /// Creates a new [AutoTuner] keeping the capacity between `min_capacity` and
+    /// `max_capacity`, and making a decision every `window` samples.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if `min_capacity` or `window` is 0, or if `min_capacity > max_capacity`.
+    pub fn new(min_capacity: usize, max_capacity: usize, window: usize) -> Self {
+        if min_capacity == 0 || window == 0 || min_capacity > max_capacity {
+            panic!(
+                "Invalid AutoTuner bounds ({}..={}, window of {})",
+                min_capacity, max_capacity, window
+            );
+        }
+        Self {
+            min_capacity,
+            max_capacity,
+            window,
+            samples: 0,
+            peak: 0,
+            overflows: 0,
+            on_resize: None,
+        }
+    }
+
+    /// Sets a callback invoked with every resize the [AutoTuner] makes.
+    pub fn on_resize(mut self, callback: impl FnMut(ResizeDecision) + Send + 'static) -> Self {
+        self.on_resize = Some(Box::new(callback));
+        self
+    }
+
+    /// Records that an enqueue was rejected because the ring was full.
+    pub fn record_overflow(&mut self) {
+        self.overflows += 1;
+    }
+
+    /// Samples the occupancy of `rb`, resizing it at the end of each window if needed.
+    /// Returns the [ResizeDecision] if `rb` was resized.
+    ///
+    /// A poisoned `rb` (see [RotatingBuffer::is_poisoned]) cannot be resized, so it is
+    /// neither sampled nor tuned.
+    pub fn observe(&mut self, rb: &mut RotatingBuffer) -> Option<ResizeDecision> {
+        if rb.is_poisoned() {
+            return None;
+        }
+        self.peak = self.peak.max(rb.len());
+        self.samples += 1;
+        if self.samples < self.window {
+            return None;
+        }
+
+        let from = rb.capacity();
+        let to = if self.overflows > 0 || self.peak == from {
+            from.saturating_mul(2)
+        } else if self.peak < from / 4 {
+            (from / 2).max(self.peak.saturating_mul(2))
+        } else {
+            from
+        }
+        .clamp(self.min_capacity, self.max_capacity)
+        .max(rb.len());
+        let decision = ResizeDecision {
+            from,
+            to,
+            peak: self.peak,
+            overflows: self.overflows,
+        };
+        self.samples = 0;
+        self.peak = 0;
+        self.overflows = 0;
+
+        if to == from {
+            return None;
+        }
+        rb.resize(to)
+            .expect("`to` is non-zero and holds every queued byte, and `rb` is not poisoned");
+        if let Some(callback) = &mut self.on_resize {
+            callback(decision);
+        }
+        Some(decision)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_grows_on_overflow_and_shrinks_when_idle() {
+        let decisions = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&decisions);
+        let mut tuner =
+            AutoTuner::new(4, 16, 2).on_resize(move |decision| log.lock().unwrap().push(decision));
+        let mut rb = RotatingBuffer::try_new(8).unwrap();
+
+        rb.enqueue_partial(&[0; 8]);
+        if rb.enqueue(0).is_err() {
+            tuner.record_overflow();
+        }
+        assert_eq!(tuner.observe(&mut rb), None);
+        assert_eq!(tuner.observe(&mut rb).map(|d| d.to), Some(16));
+        // Already at the maximum, so a full ring leaves it as is.
+        rb.enqueue_partial(&[0; 8]);
+        tuner.observe(&mut rb);
+        assert_eq!(tuner.observe(&mut rb), None);
+        assert_eq!(rb.capacity(), 16);
+
+        rb.consume_head(15);
+        tuner.observe(&mut rb);
+        assert_eq!(tuner.observe(&mut rb).map(|d| d.to), Some(8));
+        tuner.observe(&mut rb);
+        tuner.observe(&mut rb);
+        tuner.observe(&mut rb);
+        tuner.observe(&mut rb);
+        // Never shrinks below the minimum.
+        assert_eq!(rb.capacity(), 4);
+        assert_eq!(rb.len(), 1);
+
+        let decisions = decisions.lock().unwrap();
+        assert_eq!(
+            decisions.iter().map(|d| d.to).collect::<Vec<_>>(),
+            vec![16, 8, 4]
+        );
+        assert_eq!(decisions[0].overflows, 1);
+    }
+
+    #[test]
+    fn test_skips_poisoned_ring() {
+        let mut tuner = AutoTuner::new(4, 16, 1);
+        let mut rb = RotatingBuffer::try_new(8).unwrap();
+        rb.enqueue_partial(&[0; 8]);
+        rb.poisoned = true;
+        assert_eq!(tuner.observe(&mut rb), None);
+        assert_eq!(rb.capacity(), 8);
+    }
+}
@@ -0,0 +1,195 @@
+//! Tracking of the boundaries between the batches of bytes enqueued into a
+//! [RotatingBuffer].
+
+use std::borrow::Cow;
+use std::collections::{vec_deque, VecDeque};
+
+use crate::RotatingBuffer;
+
+/// The lengths of the batches of an untracked [RotatingBuffer]: none, so its whole queue is
+/// yielded as one batch.
+static UNTRACKED: VecDeque<usize> = VecDeque::new();
+
+impl RotatingBuffer {
+    /// Starts recording the boundary between every batch of bytes enqueued from now on, so
+    /// that [RotatingBuffer::batches] can yield the queue grouped as it was written.  The
+    /// bytes already queued count as a single batch.  Calling this again keeps the
+    /// boundaries already recorded.
+    ///
+    /// Every enqueueing call, such as [RotatingBuffer::enqueue_slice],
+    /// [RotatingBuffer::enqueue_vectored] or [RotatingBuffer::enqueue_frame], forms one
+    /// batch (a [RotatingBuffer::enqueue_frame_chunked] call forms one per chunk).
+    /// Dequeueing shrinks the batches from the front, and truncating from the back.  In
+    /// place edits that remove bytes from the middle of the queue, such as
+    /// [RotatingBuffer::dedup_within], shrink the batches from the back, so they keep
+    /// adding up to the queued length but no longer match the original boundaries.
+    pub fn enable_batch_tracking(&mut self) {
+        if self.batches.is_none() {
+            let len = self.len();
+            self.batches = Some((len > 0).then_some(len).into_iter().collect());
+        }
+    }
+
+    /// Stops recording batch boundaries, forgetting those already recorded.
+    pub fn disable_batch_tracking(&mut self) {
+        self.batches = None;
+    }
+
+    /// Returns whether batch boundaries are being recorded (see
+    /// [RotatingBuffer::enable_batch_tracking]).
+    pub fn tracks_batches(&self) -> bool {
+        self.batches.is_some()
+    }
+
+    /// Returns an iterator over the queued bytes grouped into the batches they were
+    /// enqueued in, from oldest to newest.  If batch tracking is not enabled, the whole
+    /// queue is yielded as a single batch.
+    ///
+    /// Batches are borrowed, except for the one (if any) spanning the wrap around the end
+    /// of the backing storage, which is copied.
+    pub fn batches(&self) -> Batches<'_> {
+        Batches {
+            rb: self,
+            lens: self.batches.as_ref().unwrap_or(&UNTRACKED).iter(),
+            pos: 0,
+        }
+    }
+
+    /// Returns the lengths of the queued batches, oldest first, if batch tracking is
+    /// enabled.
+    pub(crate) fn batch_lens(&self) -> Option<&VecDeque<usize>> {
+        self.batches.as_ref()
+    }
+
+    /// Records the last `n` queued bytes, which were just enqueued, as a batch.
+    pub(crate) fn record_batch(&mut self, n: usize) {
+        if let Some(batches) = &mut self.batches {
+            if n > 0 {
+                batches.push_back(n);
+            }
+        }
+    }
+
+    /// Runs `enqueue`, recording everything it enqueues as a single batch even if it is
+    /// written in several steps.
+    pub(crate) fn as_one_batch<T>(&mut self, enqueue: impl FnOnce(&mut Self) -> T) -> T {
+        let (batches, len) = (self.batches.take(), self.len());
+        let result = enqueue(self);
+        self.batches = batches;
+        self.record_batch(self.len() - len);
+        result
+    }
+
+    /// Removes the bytes at positions `start..end`, which are about to be removed from the
+    /// queue, from the batches they belong to, dropping the batches left empty.
+    pub(crate) fn forget_batched(&mut self, start: usize, end: usize) {
+        let Some(batches) = &mut self.batches else {
+            return;
+        };
+        let (mut batch_start, mut emptied) = (0, None::<(usize, usize)>);
+        for (i, len) in batches.iter_mut().enumerate() {
+            let batch_end = batch_start + *len;
+            *len -= batch_end.min(end).saturating_sub(batch_start.max(start));
+            if *len == 0 {
+                emptied = Some((emptied.map_or(i, |(first, _)| first), i + 1));
+            }
+            if batch_end >= end {
+                break;
+            }
+            batch_start = batch_end;
+        }
+        // The removed bytes are contiguous, so so are the batches they emptied.
+        if let Some((first, last)) = emptied {
+            batches.drain(first..last);
+        }
+    }
+}
+
+/// An iterator over the queued bytes of a [RotatingBuffer], grouped into the batches they
+/// were enqueued in.  Created by [RotatingBuffer::batches].
+#[derive(Debug, Clone)]
+pub struct Batches<'a> {
+    rb: &'a RotatingBuffer,
+    /// The lengths of the batches not yet yielded.
+    lens: vec_deque::Iter<'a, usize>,
+    /// The position of the start of the next batch.
+    pos: usize,
+}
+
+impl<'a> Iterator for Batches<'a> {
+    type Item = Cow<'a, [u8]>;
+
+    fn next(&mut self) -> Option<Cow<'a, [u8]>> {
+        let len = self.rb.len();
+        // The recorded batches add up to the queued length, so this is only reached
+        // without tracking.
+        let n = match self.lens.next() {
+            Some(&n) => n,
+            None if self.pos < len => len - self.pos,
+            None => return None,
+        };
+        let batch = match self.rb.range_segments(self.pos, self.pos + n) {
+            (whole, []) => Cow::Borrowed(whole),
+            (first, second) => Cow::Owned([first, second].concat()),
+        };
+        self.pos += n;
+        Some(batch)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = match self.lens.len() {
+            0 => usize::from(self.pos < self.rb.len()),
+            len => len,
+        };
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Batches<'_> {}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn collect(rb: &RotatingBuffer) -> Vec<Vec<u8>> {
+        rb.batches().map(|batch| batch.into_owned()).collect()
+    }
+
+    #[test]
+    fn test_batches() {
+        let mut rb = RotatingBuffer::try_new(13).unwrap();
+        rb.enqueue_partial(&[0; 5]);
+        assert_eq!(collect(&rb), vec![vec![0; 5]]);
+        rb.enable_batch_tracking();
+        rb.consume_head(5);
+        assert!(rb.batches().next().is_none());
+
+        rb.enqueue_slice(&[1, 2, 3]).unwrap();
+        rb.enqueue(4).unwrap();
+        rb.enqueue_vectored(&[&[5, 6], &[7]]).unwrap();
+        rb.enqueue_frame(&[8]).unwrap(); // Wraps around
+        let batches = rb.batches();
+        assert_eq!(batches.len(), 4);
+        assert!(matches!(batches.last(), Some(Cow::Owned(_))));
+        assert_eq!(
+            collect(&rb),
+            vec![
+                vec![1, 2, 3],
+                vec![4],
+                vec![5, 6, 7],
+                vec![0, 0, 0, 0, 1, 8]
+            ]
+        );
+
+        // Removals shrink the batches they touch, dropping those left empty.
+        rb.skip(4);
+        rb.truncate_back(5);
+        assert_eq!(collect(&rb), vec![vec![5, 6, 7], vec![0]]);
+        rb.dequeue();
+        assert_eq!(collect(&rb), vec![vec![6, 7], vec![0]]);
+
+        rb.disable_batch_tracking();
+        assert_eq!(collect(&rb), vec![vec![6, 7, 0]]);
+    }
+}
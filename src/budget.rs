@@ -0,0 +1,165 @@
+//! A byte quota shared by many [RotatingBuffer]s.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::RotatingBuffer;
+
+/// A [MemoryBudget] caps the total number of bytes queued across every [RotatingBuffer]
+/// attached to it, on top of each ring's own capacity.  Clones are handles to the same
+/// budget, so it can be shared between threads and connections.
+///
+/// While attached, a ring's [RotatingBuffer::remaining_capacity] is limited to what is left
+/// of the budget, so enqueues fail (or write less) once the aggregate limit is reached, and
+/// bytes return to the budget as they are dequeued.  The limit is checked once per enqueue
+/// operation, so enqueues racing on different threads can overshoot it by at most the
+/// size of the operations in flight.
+#[derive(Debug, Clone)]
+pub struct MemoryBudget(Arc<Shared>);
+
+#[derive(Debug)]
+struct Shared {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+impl MemoryBudget {
+    /// Creates a new [MemoryBudget] allowing up to `limit` bytes to be queued in total.
+    pub fn new(limit: usize) -> Self {
+        Self(Arc::new(Shared {
+            limit,
+            used: AtomicUsize::new(0),
+        }))
+    }
+
+    /// Returns the maximum number of bytes that may be queued across the attached rings.
+    pub fn limit(&self) -> usize {
+        self.0.limit
+    }
+
+    /// Returns the number of bytes currently queued across the attached rings.
+    pub fn used(&self) -> usize {
+        self.0.used.load(Ordering::Acquire)
+    }
+
+    /// Returns how many more bytes may be queued before the limit is reached.
+    pub fn available(&self) -> usize {
+        self.limit().saturating_sub(self.used())
+    }
+}
+
+/// The share of a [MemoryBudget] charged to one [RotatingBuffer], returned to the budget
+/// when dropped.
+#[derive(Debug)]
+pub(crate) struct BudgetCharge {
+    budget: MemoryBudget,
+    charged: usize,
+}
+
+impl BudgetCharge {
+    pub(crate) fn available(&self) -> usize {
+        self.budget.available()
+    }
+
+    pub(crate) fn charge(&mut self, n: usize) {
+        self.budget.0.used.fetch_add(n, Ordering::AcqRel);
+        self.charged += n;
+    }
+
+    pub(crate) fn release(&mut self, n: usize) {
+        let n = n.min(self.charged);
+        self.budget.0.used.fetch_sub(n, Ordering::AcqRel);
+        self.charged -= n;
+    }
+}
+
+impl Drop for BudgetCharge {
+    fn drop(&mut self) {
+        self.release(self.charged);
+    }
+}
+
+impl RotatingBuffer {
+    /// Attaches the [RotatingBuffer] to `budget`, replacing any budget it was attached to.
+    /// The bytes already queued are charged to it straight away, even if that exceeds its
+    /// limit, in which case nothing more can be enqueued until enough has been dequeued.
+    pub fn attach_budget(&mut self, budget: &MemoryBudget) {
+        let mut charge = BudgetCharge {
+            budget: budget.clone(),
+            charged: 0,
+        };
+        charge.charge(self.len());
+        self.budget = Some(charge);
+    }
+
+    /// Detaches the [RotatingBuffer] from its [MemoryBudget], returning its queued bytes to
+    /// the budget.
+    pub fn detach_budget(&mut self) {
+        self.budget = None;
+    }
+
+    /// Returns the [MemoryBudget] the [RotatingBuffer] is attached to, if any.
+    pub fn budget(&self) -> Option<&MemoryBudget> {
+        self.budget.as_ref().map(|charge| &charge.budget)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::RotBufError;
+
+    #[test]
+    fn test_limits_total_across_rings() {
+        let budget = MemoryBudget::new(6);
+        let mut a = RotatingBuffer::try_new(8).unwrap();
+        let mut b = RotatingBuffer::try_new(8).unwrap();
+        a.attach_budget(&budget);
+        b.attach_budget(&budget);
+
+        assert_eq!(a.enqueue_slice(&[1; 4]), Ok(4));
+        assert_eq!(b.remaining_capacity(), 2);
+        assert_eq!(b.enqueue_slice(&[2; 4]).unwrap_err().written(), 2);
+        assert!(b.enqueue(2).is_err());
+        assert_eq!(
+            b.fill(2, 1),
+            Err(RotBufError::Incomplete {
+                written: 0,
+                remaining: 1
+            })
+        );
+        assert_eq!(b.enqueue_iter(std::iter::repeat(2)), 0);
+        assert_eq!(budget.used(), 6);
+
+        // Transactions cannot stage past the budget either.
+        let mut txn = b.begin();
+        assert_eq!(txn.write(&[3; 2]).unwrap_err().written(), 0);
+        txn.abort();
+
+        // Dequeued bytes return to the budget, as do the bytes of a dropped ring.
+        a.consume_head(3);
+        assert_eq!(b.enqueue_iter(std::iter::repeat(2)), 3);
+        drop(a);
+        assert_eq!(budget.available(), 1);
+        b.clear();
+        assert_eq!(budget.used(), 0);
+    }
+
+    #[test]
+    fn test_attach_charges_queued_bytes() {
+        let budget = MemoryBudget::new(2);
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[1, 2, 3]);
+        rb.attach_budget(&budget);
+        assert_eq!(budget.used(), 3);
+        assert_eq!(rb.remaining_capacity(), 0);
+        rb.dequeue();
+        rb.dequeue();
+        assert_eq!(rb.remaining_capacity(), 1);
+        rb.detach_budget();
+        assert_eq!(budget.used(), 0);
+        assert_eq!(rb.remaining_capacity(), 3);
+        assert!(rb.budget().is_none());
+    }
+}
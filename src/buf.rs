@@ -0,0 +1,127 @@
+//! [bytes] trait implementations for [RotatingBuffer].
+
+use std::io::IoSlice;
+
+use bytes::buf::UninitSlice;
+use bytes::{Buf, BufMut};
+
+use crate::RotatingBuffer;
+
+/// Reads the queued bytes in place, dequeueing them as they are advanced past.
+///
+/// [RotatingBuffer::advance] returns a [Result] rather than panicking, and takes precedence
+/// over [Buf::advance] in method call syntax; call `Buf::advance(&mut rb, n)` for the
+/// panicking form required by the trait.
+impl Buf for RotatingBuffer {
+    fn remaining(&self) -> usize {
+        self.len()
+    }
+
+    fn chunk(&self) -> &[u8] {
+        self.segments().0
+    }
+
+    fn advance(&mut self, cnt: usize) {
+        if cnt > self.len() {
+            panic!(
+                "Cannot advance by {} past the end of a RotatingBuffer of length {}",
+                cnt,
+                self.len()
+            );
+        }
+        self.consume_head(cnt);
+    }
+
+    fn chunks_vectored<'a>(&'a self, dst: &mut [IoSlice<'a>]) -> usize {
+        let mut filled = 0;
+        let (first, second) = self.segments();
+        for (slot, segment) in dst.iter_mut().zip([first, second]) {
+            if segment.is_empty() {
+                break;
+            }
+            *slot = IoSlice::new(segment);
+            filled += 1;
+        }
+        filled
+    }
+}
+
+/// Writes directly into the free space past the tail, enqueueing bytes as they are
+/// advanced past.  As with any [BufMut], the `put_*` helpers panic if there is not enough
+/// [RotatingBuffer::remaining_capacity] left.
+unsafe impl BufMut for RotatingBuffer {
+    fn remaining_mut(&self) -> usize {
+        self.remaining_capacity()
+    }
+
+    unsafe fn advance_mut(&mut self, cnt: usize) {
+        if cnt > self.remaining_capacity() {
+            panic!(
+                "Cannot advance by {} past the free space of a RotatingBuffer ({} bytes)",
+                cnt,
+                self.remaining_capacity()
+            );
+        }
+        self.commit_tail(cnt);
+    }
+
+    fn chunk_mut(&mut self) -> &mut UninitSlice {
+        UninitSlice::new(self.free_segments_mut().0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_buf() {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        rb.enqueue_partial(&[0; 4]);
+        rb.consume_head(4);
+        rb.enqueue_partial(&[0, 1, 0, 0, 0, 2]); // Wraps around
+        assert_eq!(Buf::remaining(&rb), 6);
+        assert_eq!(rb.chunk(), &[0, 1]);
+
+        {
+            let mut slices = [IoSlice::new(&[]); 3];
+            assert_eq!(rb.chunks_vectored(&mut slices), 2);
+            assert_eq!(&*slices[1], &[0, 0, 0, 2]);
+        }
+
+        assert_eq!(rb.get_u16(), 1);
+        assert_eq!(rb.get_u32(), 2);
+        assert!(!rb.has_remaining());
+        assert_eq!(rb.chunks_vectored(&mut [IoSlice::new(&[]); 2]), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "past the end")]
+    fn test_advance_past_end_panics() {
+        let mut rb = RotatingBuffer::try_new(2).unwrap();
+        rb.enqueue_partial(&[1]);
+        Buf::advance(&mut rb, 2);
+    }
+
+    #[test]
+    fn test_buf_mut() {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        rb.enqueue_partial(&[0; 4]);
+        rb.consume_head(4);
+        assert_eq!(rb.chunk_mut().len(), 2);
+        rb.put_u32(0x0102_0304); // Wraps around
+        rb.put_u8(5);
+        assert_eq!(rb.remaining_mut(), 1);
+        assert_eq!(rb.last_n(5).to_vec(), vec![1, 2, 3, 4, 5]);
+        rb.put_bytes(6, 1);
+        assert!(rb.at_capacity());
+        assert_eq!(rb.chunk_mut().len(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_put_past_capacity_panics() {
+        RotatingBuffer::try_new(2).unwrap().put_u32(0);
+    }
+}
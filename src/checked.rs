@@ -0,0 +1,296 @@
+//! A [RotatingBuffer] checked against a [VecDeque] model, for differential testing.
+
+use std::collections::VecDeque;
+use std::ops::Deref;
+
+use bytes::Bytes;
+
+use crate::{IncompleteEnqueue, RotBufError, RotatingBuffer, RotatingBufferAtCapacity};
+
+/// The [CheckedBuffer] mirrors every mutating operation on a [RotatingBuffer] into a
+/// [VecDeque] model, and panics as soon as the two disagree on a result or on the queued
+/// bytes.
+///
+/// It is meant for integration tests: drive it with a real workload (or a fuzzer), and any
+/// divergence from a plain queue is caught at the operation that caused it.  Read-only
+/// methods are available through [Deref].
+#[derive(Debug)]
+pub struct CheckedBuffer {
+    rb: RotatingBuffer,
+    model: VecDeque<u8>,
+}
+
+impl CheckedBuffer {
+    /// Creates a new [CheckedBuffer] with the given capacity.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if `capacity` is 0.  [CheckedBuffer::try_new] reports that as an error
+    /// instead.
+    pub fn new(capacity: usize) -> Self {
+        match Self::try_new(capacity) {
+            Ok(cb) => cb,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Creates a new [CheckedBuffer] as in [CheckedBuffer::new], returning an [Err] with
+    /// [RotBufError::InvalidCapacity] if `capacity` is 0.
+    pub fn try_new(capacity: usize) -> Result<Self, RotBufError> {
+        Ok(Self {
+            rb: RotatingBuffer::try_new(capacity)?,
+            model: VecDeque::with_capacity(capacity),
+        })
+    }
+
+    /// Consumes the [CheckedBuffer], returning the [RotatingBuffer].
+    pub fn into_inner(self) -> RotatingBuffer {
+        self.rb
+    }
+
+    /// Panics if the [RotatingBuffer] and the model disagree on the queued bytes, or if the
+    /// [RotatingBuffer]'s internal invariants are broken.  Called after every operation.
+    #[track_caller]
+    pub fn check(&self) {
+        let report = self.rb.debug_validate();
+        assert!(report.is_valid(), "{}", report);
+        assert_eq!(
+            self.rb.len(),
+            self.model.len(),
+            "length diverged from the model"
+        );
+        assert_eq!(
+            self.rb.remaining_capacity(),
+            self.rb.capacity() - self.model.len(),
+            "remaining capacity diverged from the model"
+        );
+        let (first, second) = self.rb.segments();
+        let (model_first, model_second) = self.model.as_slices();
+        assert!(
+            first
+                .iter()
+                .chain(second)
+                .eq(model_first.iter().chain(model_second)),
+            "contents diverged from the model: {:?} != {:?}",
+            [first, second].concat(),
+            self.model
+        );
+    }
+
+    /// Checks the result of an operation against the model's, then the state.
+    #[track_caller]
+    fn agree<T: PartialEq + std::fmt::Debug>(&self, actual: T, expected: T) -> T {
+        assert_eq!(actual, expected, "result diverged from the model");
+        self.check();
+        actual
+    }
+
+    /// See [RotatingBuffer::enqueue].
+    #[track_caller]
+    pub fn enqueue(&mut self, value: u8) -> Result<(), RotatingBufferAtCapacity> {
+        let expected = match self.model.len() < self.rb.capacity() {
+            true => {
+                self.model.push_back(value);
+                Ok(())
+            }
+            false => Err(RotatingBufferAtCapacity(value)),
+        };
+        let actual = self.rb.enqueue(value);
+        self.agree(actual, expected)
+    }
+
+    /// See [RotatingBuffer::enqueue_slice].
+    #[track_caller]
+    pub fn enqueue_slice<'a>(&mut self, src: &'a [u8]) -> Result<usize, IncompleteEnqueue<'a>> {
+        let written = src.len().min(self.rb.capacity() - self.model.len());
+        self.model.extend(&src[..written]);
+        let expected = match written == src.len() {
+            true => Ok(written),
+            false => Err(IncompleteEnqueue {
+                written,
+                remainder: &src[written..],
+            }),
+        };
+        let actual = self.rb.enqueue_slice(src);
+        self.agree(actual, expected)
+    }
+
+    /// See [RotatingBuffer::enqueue_iter].
+    #[track_caller]
+    pub fn enqueue_iter<I: IntoIterator<Item = u8>>(&mut self, iter: I) -> usize {
+        let items: Vec<u8> = iter
+            .into_iter()
+            .take(self.rb.remaining_capacity())
+            .collect();
+        self.model.extend(&items);
+        let actual = self.rb.enqueue_iter(items.iter().copied());
+        self.agree(actual, items.len())
+    }
+
+    /// See [RotatingBuffer::fill].
+    #[track_caller]
+    pub fn fill(&mut self, value: u8, n: usize) -> Result<(), RotBufError> {
+        let expected = match n <= self.rb.capacity() - self.model.len() {
+            true => {
+                self.model.extend(std::iter::repeat_n(value, n));
+                Ok(())
+            }
+            false => Err(RotBufError::Incomplete {
+                written: 0,
+                remaining: n,
+            }),
+        };
+        let actual = self.rb.fill(value, n);
+        self.agree(actual, expected)
+    }
+
+    /// See [RotatingBuffer::dequeue].
+    #[track_caller]
+    pub fn dequeue(&mut self) -> Option<u8> {
+        let expected = self.model.pop_front();
+        let actual = self.rb.dequeue();
+        self.agree(actual, expected)
+    }
+
+    /// See [RotatingBuffer::dequeue_bytes].
+    #[track_caller]
+    pub fn dequeue_bytes(&mut self, n: usize) -> Option<Bytes> {
+        let expected = (n <= self.model.len()).then(|| self.model.drain(..n).collect());
+        let actual = self.rb.dequeue_bytes(n);
+        self.agree(actual, expected)
+    }
+
+    /// See [RotatingBuffer::skip].
+    #[track_caller]
+    pub fn skip(&mut self, n: usize) -> usize {
+        let expected = n.min(self.model.len());
+        self.model.drain(..expected);
+        let actual = self.rb.skip(n);
+        self.agree(actual, expected)
+    }
+
+    /// See [RotatingBuffer::truncate_front].
+    #[track_caller]
+    pub fn truncate_front(&mut self, n: usize) -> usize {
+        let expected = n.min(self.model.len());
+        self.model.drain(..expected);
+        let actual = self.rb.truncate_front(n);
+        self.agree(actual, expected)
+    }
+
+    /// See [RotatingBuffer::truncate_back].
+    #[track_caller]
+    pub fn truncate_back(&mut self, n: usize) -> usize {
+        let expected = n.min(self.model.len());
+        self.model.truncate(self.model.len() - expected);
+        let actual = self.rb.truncate_back(n);
+        self.agree(actual, expected)
+    }
+
+    /// See [RotatingBuffer::swap_remove_back].
+    #[track_caller]
+    pub fn swap_remove_back(&mut self, pos: usize) -> Option<u8> {
+        let expected = self.model.swap_remove_back(pos);
+        let actual = self.rb.swap_remove_back(pos);
+        self.agree(actual, expected)
+    }
+
+    /// See [RotatingBuffer::dedup_within].
+    #[track_caller]
+    pub fn dedup_within(&mut self, window: usize) -> usize {
+        let mut kept: Vec<u8> = Vec::with_capacity(self.model.len());
+        for value in self.model.drain(..) {
+            if !kept[kept.len().saturating_sub(window)..].contains(&value) {
+                kept.push(value);
+            }
+        }
+        let expected = self.rb.len() - kept.len();
+        self.model.extend(kept);
+        let actual = self.rb.dedup_within(window);
+        self.agree(actual, expected)
+    }
+
+    /// See [RotatingBuffer::overwrite_pos].
+    #[track_caller]
+    pub fn overwrite_pos(&mut self, pos: usize, src: &[u8]) -> Result<(), RotBufError> {
+        let len = self.model.len();
+        let expected = match pos.checked_add(src.len()) {
+            Some(end) if end <= len => {
+                for (slot, &value) in self.model.range_mut(pos..end).zip(src) {
+                    *slot = value;
+                }
+                Ok(())
+            }
+            _ => Err(RotBufError::OutOfBounds {
+                pos: pos.max(len),
+                len,
+            }),
+        };
+        let actual = self.rb.overwrite_pos(pos, src);
+        self.agree(actual, expected)
+    }
+
+    /// See [RotatingBuffer::drain].  The drained bytes are collected.
+    #[track_caller]
+    pub fn drain(&mut self, start: usize, end: usize) -> Vec<u8> {
+        let expected: Vec<u8> = self.model.drain(start..end).collect();
+        let actual: Vec<u8> = self.rb.drain(start..end).collect();
+        self.agree(actual, expected)
+    }
+
+    /// See [RotatingBuffer::clear].
+    #[track_caller]
+    pub fn clear(&mut self) {
+        self.model.clear();
+        self.rb.clear();
+        self.check();
+    }
+}
+
+impl Deref for CheckedBuffer {
+    type Target = RotatingBuffer;
+
+    fn deref(&self) -> &RotatingBuffer {
+        &self.rb
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_agrees_with_model() {
+        let mut cb = CheckedBuffer::new(5);
+        for round in 0..20u8 {
+            cb.enqueue_slice(&[round; 3]).ok();
+            cb.enqueue(round).ok();
+            cb.enqueue_iter(0..round % 3);
+            cb.fill(round, 2).ok();
+            cb.swap_remove_back(round as usize % 4);
+            cb.dedup_within(round as usize % 3);
+            cb.dequeue();
+            cb.overwrite_pos(1, &[round, round]).ok();
+            if round % 3 == 0 {
+                cb.drain(1, cb.len().min(3));
+            }
+            cb.dequeue_bytes(2);
+            cb.skip(1);
+            cb.truncate_back(round as usize % 2);
+            cb.truncate_front(1);
+        }
+        cb.clear();
+        assert!(cb.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "diverged from the model")]
+    fn test_detects_divergence() {
+        let mut cb = CheckedBuffer::new(4);
+        cb.enqueue_slice(&[1, 2]).unwrap();
+        // Mutate the ring behind the model's back.
+        cb.rb.dequeue();
+        cb.enqueue(3).unwrap();
+    }
+}
@@ -0,0 +1,251 @@
+//! Compression adapters that stage compressed bytes in, and decompress bytes out of, a
+//! [RotatingBuffer].
+
+use std::io::{self, ErrorKind, Read, Write};
+
+use crate::RotatingBuffer;
+
+/// Writes into a [RotatingBuffer], reporting `WouldBlock` once the ring is full.
+#[derive(Debug)]
+struct RingSink<'a>(&'a mut RotatingBuffer);
+
+impl Write for RingSink<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.0.enqueue_partial(buf) {
+            0 if !buf.is_empty() => Err(ErrorKind::WouldBlock.into()),
+            written => Ok(written),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads by dequeueing from a [RotatingBuffer], reporting end of stream once it is empty.
+#[derive(Debug)]
+struct RingSource<'a>(&'a mut RotatingBuffer);
+
+impl Read for RingSource<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let (queued, _) = self.0.segments();
+        let n = queued.len().min(buf.len());
+        buf[..n].copy_from_slice(&queued[..n]);
+        self.0.consume_head(n);
+        Ok(n)
+    }
+}
+
+enum Encoder<'a> {
+    #[cfg(feature = "flate2")]
+    Deflate(flate2::write::DeflateEncoder<RingSink<'a>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, RingSink<'a>>),
+}
+
+/// The [CompressingWriter] compresses everything written to it and enqueues the
+/// compressed bytes into a [RotatingBuffer].
+///
+/// When the ring fills up, writes fail with `WouldBlock`; the compressor keeps whatever it
+/// could not enqueue, so dequeue from the ring and retry.  [CompressingWriter::finish] must
+/// be called to enqueue the end of the compressed stream.
+pub struct CompressingWriter<'a> {
+    encoder: Encoder<'a>,
+}
+
+impl<'a> CompressingWriter<'a> {
+    /// Creates a [CompressingWriter] producing a raw deflate stream into `rb`, at a level
+    /// from 0 (no compression) to 9 (best).
+    #[cfg(feature = "flate2")]
+    pub fn deflate(rb: &'a mut RotatingBuffer, level: u32) -> Self {
+        let level = flate2::Compression::new(level);
+        Self {
+            encoder: Encoder::Deflate(flate2::write::DeflateEncoder::new(RingSink(rb), level)),
+        }
+    }
+
+    /// Creates a [CompressingWriter] producing a zstd frame into `rb`.  A `level` of 0 uses
+    /// zstd's default level.
+    #[cfg(feature = "zstd")]
+    pub fn zstd(rb: &'a mut RotatingBuffer, level: i32) -> io::Result<Self> {
+        Ok(Self {
+            encoder: Encoder::Zstd(zstd::stream::write::Encoder::new(RingSink(rb), level)?),
+        })
+    }
+
+    /// Returns the [RotatingBuffer] being written to, e.g. to dequeue from it after a
+    /// write fails with `WouldBlock`.
+    pub fn get_mut(&mut self) -> &mut RotatingBuffer {
+        match &mut self.encoder {
+            #[cfg(feature = "flate2")]
+            Encoder::Deflate(encoder) => encoder.get_mut().0,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.get_mut().0,
+        }
+    }
+
+    /// Enqueues the end of the compressed stream.
+    ///
+    /// Fails with `WouldBlock` if the ring fills up before the stream is complete, in which
+    /// case dequeue from the ring and call this again.
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        match &mut self.encoder {
+            #[cfg(feature = "flate2")]
+            Encoder::Deflate(encoder) => encoder.try_finish(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.do_finish(),
+        }
+    }
+
+    /// Enqueues the end of the compressed stream, returning the [RotatingBuffer].
+    ///
+    /// Fails with `WouldBlock` if the ring fills up before the stream is complete, in which
+    /// case the rest of the stream is lost.  Use [CompressingWriter::try_finish] first when
+    /// the ring may be too small to hold it.
+    pub fn finish(mut self) -> io::Result<&'a mut RotatingBuffer> {
+        self.try_finish()?;
+        let sink = match self.encoder {
+            #[cfg(feature = "flate2")]
+            Encoder::Deflate(encoder) => encoder.finish()?,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.finish()?,
+        };
+        Ok(sink.0)
+    }
+}
+
+impl Write for CompressingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.encoder {
+            #[cfg(feature = "flate2")]
+            Encoder::Deflate(encoder) => encoder.write(buf),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.encoder {
+            #[cfg(feature = "flate2")]
+            Encoder::Deflate(encoder) => encoder.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+enum Decoder<'a> {
+    #[cfg(feature = "flate2")]
+    Deflate(flate2::read::DeflateDecoder<RingSource<'a>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::read::Decoder<'static, io::BufReader<RingSource<'a>>>),
+}
+
+/// The [DecompressingReader] dequeues compressed bytes from a [RotatingBuffer] and reads
+/// them back decompressed.
+///
+/// An empty ring is treated as the end of the compressed stream, so the whole stream
+/// should be enqueued before reading.
+pub struct DecompressingReader<'a> {
+    decoder: Decoder<'a>,
+}
+
+impl<'a> DecompressingReader<'a> {
+    /// Creates a [DecompressingReader] over a raw deflate stream queued in `rb`.
+    #[cfg(feature = "flate2")]
+    pub fn deflate(rb: &'a mut RotatingBuffer) -> Self {
+        Self {
+            decoder: Decoder::Deflate(flate2::read::DeflateDecoder::new(RingSource(rb))),
+        }
+    }
+
+    /// Creates a [DecompressingReader] over a zstd frame queued in `rb`.
+    #[cfg(feature = "zstd")]
+    pub fn zstd(rb: &'a mut RotatingBuffer) -> io::Result<Self> {
+        Ok(Self {
+            decoder: Decoder::Zstd(zstd::stream::read::Decoder::new(RingSource(rb))?),
+        })
+    }
+}
+
+impl Read for DecompressingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.decoder {
+            #[cfg(feature = "flate2")]
+            Decoder::Deflate(decoder) => decoder.read(buf),
+            #[cfg(feature = "zstd")]
+            Decoder::Zstd(decoder) => decoder.read(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn round_trip(
+        compress: impl FnOnce(&mut RotatingBuffer) -> CompressingWriter<'_>,
+        decompress: impl FnOnce(&mut RotatingBuffer) -> DecompressingReader<'_>,
+    ) {
+        let payload = b"rotating rotating rotating rotating buffer".repeat(20);
+        let mut rb = RotatingBuffer::try_new(256).unwrap();
+        // Start part way through the ring so the compressed bytes wrap around.
+        rb.enqueue_partial(&[0; 200]);
+        rb.consume_head(200);
+
+        let mut writer = compress(&mut rb);
+        writer.write_all(&payload).unwrap();
+        let rb = writer.finish().unwrap();
+        assert!(rb.len() < payload.len());
+
+        let mut decompressed = Vec::new();
+        decompress(rb).read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, payload);
+        assert!(rb.is_empty());
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn test_deflate_round_trip() {
+        round_trip(
+            |rb| CompressingWriter::deflate(rb, 6),
+            |rb| DecompressingReader::deflate(rb),
+        );
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_round_trip() {
+        round_trip(
+            |rb| CompressingWriter::zstd(rb, 0).unwrap(),
+            |rb| DecompressingReader::zstd(rb).unwrap(),
+        );
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn test_full_ring_would_block() {
+        let payload: Vec<u8> = (0..=255).collect();
+        let mut rb = RotatingBuffer::try_new(16).unwrap();
+        let mut compressed = Vec::new();
+        let mut writer = CompressingWriter::deflate(&mut rb, 0);
+        writer.write_all(&payload).unwrap();
+        while let Err(err) = writer.try_finish() {
+            assert_eq!(err.kind(), ErrorKind::WouldBlock);
+            // Stand in for a consumer draining the ring.
+            RingSource(writer.get_mut())
+                .read_to_end(&mut compressed)
+                .unwrap();
+        }
+        RingSource(writer.finish().unwrap())
+            .read_to_end(&mut compressed)
+            .unwrap();
+
+        let mut decompressed = Vec::new();
+        flate2::read::DeflateDecoder::new(&compressed[..])
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, payload);
+    }
+}
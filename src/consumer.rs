@@ -0,0 +1,135 @@
+//! Scoped consumption of a [RotatingBuffer] that rolls back on error.
+
+use crate::{RotBufCursor, RotBufError, RotatingBuffer};
+
+impl RotatingBuffer {
+    /// Runs `f` with a [PeekConsumer] over the queued bytes.  If `f` returns [Ok], every
+    /// byte it consumed through the [PeekConsumer] is dequeued; if it returns [Err], nothing
+    /// is dequeued, as if `f` had never run.
+    ///
+    /// This makes "parse a whole message, or wait for more data" safe against leaking a
+    /// partially consumed message:
+    ///
+    /// ```
+    /// # use rotbuf::{RotatingBuffer, RotBufError};
+    /// let mut rb = RotatingBuffer::try_new(8).unwrap();
+    /// rb.enqueue_slice(&[3, 1, 2]).unwrap();
+    /// let parse = |rb: &mut RotatingBuffer| {
+    ///     rb.with_peek_consumer(|cursor| {
+    ///         let mut body = vec![0; cursor.read_u8()? as usize];
+    ///         cursor.read_exact(&mut body)?;
+    ///         Ok::<_, RotBufError>(body)
+    ///     })
+    /// };
+    /// assert!(parse(&mut rb).is_err());
+    /// assert_eq!(rb.len(), 3);
+    /// rb.enqueue(3).unwrap();
+    /// assert_eq!(parse(&mut rb).unwrap(), vec![1, 2, 3]);
+    /// assert!(rb.is_empty());
+    /// ```
+    pub fn with_peek_consumer<T, E>(
+        &mut self,
+        f: impl FnOnce(&mut PeekConsumer<'_>) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let mut consumer = PeekConsumer {
+            cursor: self.cursor(),
+        };
+        let result = f(&mut consumer);
+        let consumed = consumer.consumed();
+        if result.is_ok() {
+            self.consume_head(consumed);
+        }
+        result
+    }
+}
+
+/// A [PeekConsumer] reads through the queued bytes of a [RotatingBuffer] without
+/// dequeueing them.  Created by [RotatingBuffer::with_peek_consumer].
+///
+/// It is a forward-only [RotBufCursor] whose position is the number of bytes consumed.
+/// Reads past the end of the queue fail with [RotBufError::OutOfBounds], reporting the
+/// first position that could not be read, and consume nothing.
+#[derive(Debug)]
+pub struct PeekConsumer<'a> {
+    cursor: RotBufCursor<'a>,
+}
+
+impl PeekConsumer<'_> {
+    /// Returns how many bytes have been consumed so far.
+    pub fn consumed(&self) -> usize {
+        self.cursor.position()
+    }
+
+    /// Returns how many queued bytes have not been consumed yet.
+    pub fn remaining(&self) -> usize {
+        self.cursor.remaining()
+    }
+
+    /// Returns the next byte without consuming it, or [None] if every byte was consumed.
+    pub fn peek(&self) -> Option<u8> {
+        self.cursor.peek()
+    }
+
+    /// Consumes and returns the next byte.
+    pub fn read_u8(&mut self) -> Result<u8, RotBufError> {
+        self.cursor.read_u8()
+    }
+
+    /// Consumes exactly `dst.len()` bytes into `dst`.
+    pub fn read_exact(&mut self, dst: &mut [u8]) -> Result<(), RotBufError> {
+        let (first, second) = self.cursor.read_segments(dst.len())?;
+        dst[..first.len()].copy_from_slice(first);
+        dst[first.len()..].copy_from_slice(second);
+        Ok(())
+    }
+
+    /// Consumes `n` bytes without reading them.
+    pub fn skip(&mut self, n: usize) -> Result<(), RotBufError> {
+        self.cursor.read_segments(n).map(drop)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_rolls_back_on_error() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[0, 0, 0]);
+        rb.consume_head(3);
+        rb.enqueue_partial(&[1, 2, 3]); // Wraps around
+
+        let result = rb.with_peek_consumer(|cursor| {
+            cursor.skip(1)?;
+            let mut dst = [0; 3];
+            cursor.read_exact(&mut dst)
+        });
+        assert_eq!(result, Err(RotBufError::OutOfBounds { pos: 3, len: 3 }));
+        assert_eq!(rb.len(), 3);
+
+        let result = rb.with_peek_consumer(|cursor| {
+            let mut dst = [0; 2];
+            cursor.read_exact(&mut dst)?;
+            assert_eq!(cursor.consumed(), 2);
+            Ok::<_, RotBufError>(dst)
+        });
+        assert_eq!(result, Ok([1, 2]));
+        assert_eq!(rb.peek(), Some(3));
+    }
+
+    #[test]
+    fn test_read_past_end() {
+        let mut rb = RotatingBuffer::try_new(2).unwrap();
+        rb.enqueue(5).unwrap();
+        let result = rb.with_peek_consumer(|cursor| {
+            assert_eq!(cursor.read_u8(), Ok(5));
+            assert_eq!(cursor.peek(), None);
+            assert_eq!(cursor.remaining(), 0);
+            cursor.read_u8()
+        });
+        assert_eq!(result, Err(RotBufError::OutOfBounds { pos: 1, len: 1 }));
+        assert_eq!(rb.dequeue(), Some(5));
+    }
+}
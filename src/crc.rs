@@ -0,0 +1,157 @@
+//! Running checksums of the bytes passing through a [RotatingBuffer].
+
+use crate::RotatingBuffer;
+
+/// The reflected CRC-32 (IEEE 802.3) polynomial.
+const CRC32_POLY: u32 = 0xEDB8_8320;
+/// The reflected CRC-32C (Castagnoli) polynomial.
+const CRC32C_POLY: u32 = 0x82F6_3B78;
+
+static CRC32_TABLE: [u32; 256] = make_table(CRC32_POLY);
+static CRC32C_TABLE: [u32; 256] = make_table(CRC32C_POLY);
+
+const fn make_table(poly: u32) -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ poly
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Which CRC is maintained by [RotatingBuffer::enable_crc].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrcAlgorithm {
+    /// CRC-32 as used by zlib, gzip and Ethernet.
+    Crc32,
+    /// CRC-32C (Castagnoli) as used by iSCSI, ext4 and SCTP.
+    Crc32c,
+}
+
+impl CrcAlgorithm {
+    fn table(self) -> &'static [u32; 256] {
+        match self {
+            CrcAlgorithm::Crc32 => &CRC32_TABLE,
+            CrcAlgorithm::Crc32c => &CRC32C_TABLE,
+        }
+    }
+}
+
+/// The CRCs of every byte enqueued to, and dequeued from, a [RotatingBuffer] since
+/// [RotatingBuffer::enable_crc] was called.  Returned by [RotatingBuffer::crc].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamCrc {
+    /// The CRC of every byte enqueued.
+    pub enqueued: u32,
+    /// The CRC of every byte dequeued (or otherwise removed from the queue).
+    pub dequeued: u32,
+}
+
+/// The running (un-finalized) CRC state of a [RotatingBuffer].
+#[derive(Debug, Clone)]
+pub(crate) struct CrcState {
+    table: &'static [u32; 256],
+    enqueued: u32,
+    dequeued: u32,
+}
+
+impl CrcState {
+    fn new(algorithm: CrcAlgorithm) -> Self {
+        Self {
+            table: algorithm.table(),
+            enqueued: !0,
+            dequeued: !0,
+        }
+    }
+
+    fn update(table: &[u32; 256], mut crc: u32, bytes: &[u8]) -> u32 {
+        for &byte in bytes {
+            crc = table[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+        }
+        crc
+    }
+
+    pub(crate) fn enqueued(&mut self, bytes: &[u8]) {
+        self.enqueued = Self::update(self.table, self.enqueued, bytes);
+    }
+
+    pub(crate) fn dequeued(&mut self, bytes: &[u8]) {
+        self.dequeued = Self::update(self.table, self.dequeued, bytes);
+    }
+}
+
+impl RotatingBuffer {
+    /// Starts maintaining running CRCs of every byte enqueued and dequeued from now on,
+    /// retrievable through [RotatingBuffer::crc].  Calling this again restarts both CRCs.
+    ///
+    /// Bytes already queued are not included in either CRC, so when checking a relayed
+    /// stream end-to-end, enable the CRC before the first byte is enqueued.
+    pub fn enable_crc(&mut self, algorithm: CrcAlgorithm) {
+        self.crc = Some(Box::new(CrcState::new(algorithm)));
+    }
+
+    /// Stops maintaining CRCs.
+    pub fn disable_crc(&mut self) {
+        self.crc = None;
+    }
+
+    /// Returns the CRCs of the bytes enqueued and dequeued since [RotatingBuffer::enable_crc]
+    /// was called, or [None] if CRCs are not being maintained.
+    pub fn crc(&self) -> Option<StreamCrc> {
+        self.crc.as_ref().map(|state| StreamCrc {
+            enqueued: !state.enqueued,
+            dequeued: !state.dequeued,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_check_values() {
+        for (algorithm, check) in [
+            (CrcAlgorithm::Crc32, 0xCBF4_3926),
+            (CrcAlgorithm::Crc32c, 0xE306_9283),
+        ] {
+            let mut rb = RotatingBuffer::try_new(9).unwrap();
+            rb.enable_crc(algorithm);
+            assert_eq!(rb.crc().unwrap().enqueued, 0);
+            rb.enqueue_partial(b"12345678");
+            rb.enqueue(b'9').unwrap();
+            assert_eq!(rb.crc().unwrap().enqueued, check);
+            assert_eq!(rb.crc().unwrap().dequeued, 0);
+        }
+    }
+
+    #[test]
+    fn test_enqueued_and_dequeued_match_across_wrap() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        assert_eq!(rb.crc(), None);
+        rb.enable_crc(CrcAlgorithm::Crc32);
+        for chunk in b"123456789".chunks(3) {
+            rb.enqueue_partial(chunk);
+            rb.dequeue();
+            rb.consume_head(rb.len());
+        }
+        let crc = rb.crc().unwrap();
+        assert_eq!(crc.enqueued, 0xCBF4_3926);
+        assert_eq!(crc.enqueued, crc.dequeued);
+
+        rb.disable_crc();
+        assert_eq!(rb.crc(), None);
+    }
+}
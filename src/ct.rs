@@ -0,0 +1,39 @@
+//! Constant-time comparison of a [RotatingBuffer]'s contents.
+
+use subtle::ConstantTimeEq;
+
+use crate::RotatingBuffer;
+
+impl RotatingBuffer {
+    /// Returns whether the queued bytes equal `other`, comparing them in constant time so
+    /// that e.g. an authentication tag held in the ring does not leak through timing.
+    ///
+    /// Only the contents are protected: if the lengths differ, `false` is returned
+    /// immediately.
+    pub fn ct_eq(&self, other: &[u8]) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        let (first, second) = self.segments();
+        let (other_first, other_second) = other.split_at(first.len());
+        (first.ct_eq(other_first) & second.ct_eq(other_second)).into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_ct_eq_across_wrap() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[0, 0, 0]);
+        rb.consume_head(3);
+        rb.enqueue_partial(&[1, 2, 3]); // Wraps around
+        assert!(rb.ct_eq(&[1, 2, 3]));
+        assert!(!rb.ct_eq(&[1, 2, 4]));
+        assert!(!rb.ct_eq(&[1, 2]));
+        assert!(RotatingBuffer::try_new(1).unwrap().ct_eq(&[]));
+    }
+}
@@ -0,0 +1,128 @@
+//! A cursor walking the queued bytes of a [RotatingBuffer] without dequeueing them.
+
+use std::borrow::Cow;
+
+use crate::{RotBufError, RotatingBuffer};
+
+impl RotatingBuffer {
+    /// Returns a [RotBufCursor] positioned at the head of the queue.
+    pub fn cursor(&self) -> RotBufCursor<'_> {
+        RotBufCursor { rb: self, pos: 0 }
+    }
+}
+
+/// A [RotBufCursor] reads through the queued bytes of a [RotatingBuffer] without dequeueing
+/// them, tracking its own position.  Created by [RotatingBuffer::cursor].
+///
+/// Unlike a [crate::PeekConsumer], a [RotBufCursor] can [seek](RotBufCursor::seek) back,
+/// and can be cloned to remember a position, so a parser can make several passes over a
+/// message before deciding how much of it to dequeue.  Reads past the end of the queue fail
+/// with [RotBufError::OutOfBounds], reporting the first position that could not be read,
+/// and do not move the cursor.
+#[derive(Debug, Clone)]
+pub struct RotBufCursor<'a> {
+    rb: &'a RotatingBuffer,
+    /// The position of the next byte to read.
+    pos: usize,
+}
+
+impl<'a> RotBufCursor<'a> {
+    /// Returns the position of the next byte to read.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns how many queued bytes lie past the cursor.
+    pub fn remaining(&self) -> usize {
+        self.rb.len() - self.pos
+    }
+
+    fn out_of_bounds(&self) -> RotBufError {
+        RotBufError::OutOfBounds {
+            pos: self.pos.max(self.rb.len()),
+            len: self.rb.len(),
+        }
+    }
+
+    /// Returns the next byte without reading it, or [None] at the end of the queue.
+    pub(crate) fn peek(&self) -> Option<u8> {
+        self.rb.peek_pos(self.pos)
+    }
+
+    /// Reads the next byte.
+    pub fn read_u8(&mut self) -> Result<u8, RotBufError> {
+        let value = self.peek().ok_or_else(|| self.out_of_bounds())?;
+        self.pos += 1;
+        Ok(value)
+    }
+
+    /// Reads the next `n` bytes.  They are borrowed from the [RotatingBuffer], unless they
+    /// wrap around the end of its backing storage, in which case they are copied.
+    pub fn read_slice(&mut self, n: usize) -> Result<Cow<'a, [u8]>, RotBufError> {
+        Ok(match self.read_segments(n)? {
+            (whole, []) => Cow::Borrowed(whole),
+            (first, second) => Cow::Owned([first, second].concat()),
+        })
+    }
+
+    /// Reads the next `n` bytes as the two segments of the ring they are stored in.
+    pub(crate) fn read_segments(&mut self, n: usize) -> Result<(&'a [u8], &'a [u8]), RotBufError> {
+        if n > self.remaining() {
+            return Err(self.out_of_bounds());
+        }
+        let segments = self.rb.range_segments(self.pos, self.pos + n);
+        self.pos += n;
+        Ok(segments)
+    }
+
+    /// Moves the cursor to position `pos`, which may be anywhere from the head to the end
+    /// of the queue.
+    pub fn seek(&mut self, pos: usize) -> Result<(), RotBufError> {
+        let len = self.rb.len();
+        if pos > len {
+            return Err(RotBufError::OutOfBounds { pos, len });
+        }
+        self.pos = pos;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_multi_pass() {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        rb.enqueue_partial(&[0; 4]);
+        rb.consume_head(4);
+        rb.enqueue_partial(&[2, 7, 8, 3, 1, 2]); // Wraps around
+
+        let mut cursor = rb.cursor();
+        let len = cursor.read_u8().unwrap() as usize;
+        let start = cursor.clone();
+        assert_eq!(cursor.read_slice(len).unwrap(), &[7, 8][..]);
+        assert_eq!(cursor.read_u8(), Ok(3));
+        assert_eq!(
+            cursor.read_slice(5),
+            Err(RotBufError::OutOfBounds { pos: 6, len: 6 })
+        );
+        assert_eq!(cursor.remaining(), 2);
+
+        // A second pass from the remembered position.
+        cursor = start;
+        assert_eq!(cursor.position(), 1);
+        assert!(matches!(cursor.read_slice(2), Ok(Cow::Owned(_))));
+        cursor.seek(6).unwrap();
+        assert_eq!(
+            cursor.read_u8(),
+            Err(RotBufError::OutOfBounds { pos: 6, len: 6 })
+        );
+        assert_eq!(
+            cursor.seek(7),
+            Err(RotBufError::OutOfBounds { pos: 7, len: 6 })
+        );
+        assert_eq!(rb.len(), 6);
+    }
+}
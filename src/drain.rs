@@ -0,0 +1,148 @@
+//! Draining a range of a [RotatingBuffer] through an iterator.
+
+use std::ops::{Bound, RangeBounds};
+
+use crate::RotatingBuffer;
+
+impl RotatingBuffer {
+    /// Returns an iterator that removes the bytes at `range` of positions from the queue,
+    /// yielding them in queue order.  Use `drain(..)` to drain the whole queue.
+    ///
+    /// As with [std::collections::VecDeque::drain], the whole range is removed when the
+    /// [Drain] is dropped, even if it was not fully consumed, and the queue is left
+    /// untouched until then.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if the start of the range is after its end, or the end is past the end of
+    /// the queue.
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<'_> {
+        let len = self.len();
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.saturating_add(1),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => len,
+        };
+        if start > end || end > len {
+            panic!(
+                "Range {}..{} is out of bounds for a RotatingBuffer of length {}",
+                start, end, len
+            );
+        }
+        Drain {
+            rb: self,
+            start,
+            end,
+            front: start,
+            back: end,
+        }
+    }
+}
+
+/// An iterator removing a range of bytes from a [RotatingBuffer].  Created by
+/// [RotatingBuffer::drain].
+#[derive(Debug)]
+pub struct Drain<'a> {
+    rb: &'a mut RotatingBuffer,
+    /// The range being drained.
+    start: usize,
+    end: usize,
+    /// The positions of the next bytes to yield from the front and back.
+    front: usize,
+    back: usize,
+}
+
+impl Iterator for Drain<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.front == self.back {
+            return None;
+        }
+        let value = self.rb.peek_pos(self.front);
+        self.front += 1;
+        value
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for Drain<'_> {
+    fn next_back(&mut self) -> Option<u8> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        self.rb.peek_pos(self.back)
+    }
+}
+
+impl ExactSizeIterator for Drain<'_> {}
+
+impl Drop for Drain<'_> {
+    fn drop(&mut self) {
+        self.rb.remove_range(self.start, self.end);
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn wrapped() -> RotatingBuffer {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        rb.enqueue_partial(&[0; 4]);
+        rb.consume_head(4);
+        rb.enqueue_partial(&[1, 2, 3, 4, 5, 6]); // Wraps around
+        rb
+    }
+
+    fn contents(rb: &mut RotatingBuffer) -> Vec<u8> {
+        rb.drain(..).collect()
+    }
+
+    #[test]
+    fn test_drain_ranges() {
+        let mut rb = wrapped();
+        assert_eq!(rb.drain(..2).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(rb.drain(2..).rev().collect::<Vec<_>>(), vec![6, 5]);
+        assert_eq!(contents(&mut rb), vec![3, 4]);
+        assert!(rb.is_empty());
+
+        // Removing from the middle closes the gap from either side.
+        let mut rb = wrapped();
+        assert_eq!(rb.drain(1..3).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(contents(&mut rb), vec![1, 4, 5, 6]);
+        let mut rb = wrapped();
+        assert_eq!(rb.drain(3..=4).collect::<Vec<_>>(), vec![4, 5]);
+        rb.enqueue_partial(&[7, 8]);
+        assert_eq!(contents(&mut rb), vec![1, 2, 3, 6, 7, 8]);
+    }
+
+    #[test]
+    fn test_partially_consumed_drain_removes_whole_range() {
+        let mut rb = wrapped();
+        let mut drain = rb.drain(1..5);
+        assert_eq!(drain.len(), 4);
+        assert_eq!(drain.next(), Some(2));
+        assert_eq!(drain.next_back(), Some(5));
+        assert_eq!(drain.len(), 2);
+        drop(drain);
+        assert_eq!(contents(&mut rb), vec![1, 6]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_drain_out_of_bounds_panics() {
+        wrapped().drain(..7);
+    }
+}
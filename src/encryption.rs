@@ -0,0 +1,204 @@
+//! AES-256-GCM encryption of exported [RotatingBuffer] state, available with the
+//! `encryption` feature.
+//!
+//! An encrypted state is laid out as:
+//!
+//! | Field      | Encoding                                      |
+//! |------------|-----------------------------------------------|
+//! | magic      | `b"RBEN"`                                     |
+//! | version    | `u16` (LE)                                    |
+//! | nonce      | 12 random bytes                               |
+//! | ciphertext | [RotatingBuffer::export_state] plus GCM tag   |
+//!
+//! The magic, version and nonce are authenticated as associated data, so tampering with
+//! any part of the encrypted state is detected on import.  The state is encrypted in place
+//! and decrypted into a buffer that is zeroed once imported, so no plaintext copy of it is
+//! left behind.
+//!
+//! The same cipher also encrypts the spool files of a [crate::SpillingBuffer] (see
+//! [crate::SpillingBuffer::encrypt_spool]).
+//!
+//! # Versions
+//!
+//! The version here only covers the encrypted envelope; the plaintext carries its own
+//! version (see [crate::STATE_FORMAT_VERSION]).
+//!
+//! * **Version 1** is the current format described above, and the first one released.
+
+use std::fmt;
+use std::io::{self, ErrorKind};
+
+use aes_gcm::aead::{AeadCore, AeadInPlace, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce, Tag};
+use bytes::{Buf, BufMut, Bytes};
+use zeroize::Zeroizing;
+
+use crate::{RotBufError, RotatingBuffer};
+
+/// The version of the encrypted envelope written by [RotatingBuffer::export_state_encrypted].
+const ENVELOPE_VERSION: u16 = 1;
+
+/// Marks the start of a versioned encrypted state.
+const MAGIC: &[u8; 4] = b"RBEN";
+
+/// The length of an AES-GCM nonce.
+const NONCE_LEN: usize = 12;
+
+/// The length of an AES-GCM authentication tag.
+const TAG_LEN: usize = 16;
+
+impl RotatingBuffer {
+    /// Exports the state of the [RotatingBuffer] (see [RotatingBuffer::export_state]),
+    /// encrypted and authenticated with AES-256-GCM under `key`.  A fresh random nonce is
+    /// generated for every export.
+    pub fn export_state_encrypted(&self, key: &[u8; 32]) -> Bytes {
+        let cipher = Aes256Gcm::new(key.into());
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+        let header_len = MAGIC.len() + 2 + NONCE_LEN;
+        // Sized up front, so the plaintext is never copied by a reallocation.
+        let mut out = Vec::with_capacity(header_len + self.state_len() + TAG_LEN);
+        out.put_slice(MAGIC);
+        out.put_u16_le(ENVELOPE_VERSION);
+        out.put_slice(&nonce);
+        self.write_state(&mut out);
+        let (header, plaintext) = out.split_at_mut(header_len);
+        let tag = cipher
+            .encrypt_in_place_detached(&nonce, header, plaintext)
+            .expect("AES-GCM encryption of an in-memory buffer cannot fail");
+        out.put_slice(&tag);
+        Bytes::from(out)
+    }
+
+    /// Decrypts and imports a state produced by [RotatingBuffer::export_state_encrypted].
+    ///
+    /// Returns an [Err] with a [RotBufError::InvalidState] if `state` is malformed, was encrypted with
+    /// a different key, or has been tampered with.
+    pub fn import_state_encrypted(state: &[u8], key: &[u8; 32]) -> Result<Self, RotBufError> {
+        let header_len = header_len(state)?;
+        let (header, ciphertext) = state.split_at(header_len);
+        if ciphertext.len() < TAG_LEN {
+            return Err(RotBufError::InvalidState(
+                "encrypted state is shorter than its tag",
+            ));
+        }
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - TAG_LEN);
+
+        let cipher = Aes256Gcm::new(key.into());
+        // Zeroed when dropped, so the decrypted state does not outlive the import.
+        let mut plaintext = Zeroizing::new(ciphertext.to_vec());
+        cipher
+            .decrypt_in_place_detached(
+                Nonce::from_slice(&header[header_len - NONCE_LEN..]),
+                header,
+                &mut plaintext,
+                Tag::from_slice(tag),
+            )
+            .map_err(|_| RotBufError::InvalidState("encrypted state failed authentication"))?;
+        RotatingBuffer::import_state(&plaintext)
+    }
+}
+
+/// Encrypts the records a [crate::SpillingBuffer] spools to disk.
+///
+/// A sealed record is the ciphertext followed by the tag and a random nonce.  Each record
+/// is authenticated along with its sequence number in the spool, so records that were
+/// tampered with, reordered or replayed fail to open.
+#[derive(Clone)]
+pub(crate) struct SpoolCipher(Aes256Gcm);
+
+impl SpoolCipher {
+    pub(crate) fn new(key: &[u8; 32]) -> Self {
+        Self(Aes256Gcm::new(key.into()))
+    }
+
+    /// Encrypts `record`, the `seq`th written to the spool, in place.
+    pub(crate) fn seal(&self, seq: u64, record: &mut Vec<u8>) {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        self.0
+            .encrypt_in_place(&nonce, &seq.to_le_bytes(), record)
+            .expect("AES-GCM encryption of an in-memory buffer cannot fail");
+        record.extend_from_slice(&nonce);
+    }
+
+    /// Decrypts `record`, the `seq`th read back from the spool, in place.
+    pub(crate) fn open(&self, seq: u64, record: &mut Vec<u8>) -> io::Result<()> {
+        let failed =
+            || io::Error::new(ErrorKind::InvalidData, "spool record failed authentication");
+        let nonce_pos = record.len().checked_sub(NONCE_LEN).ok_or_else(failed)?;
+        let nonce = record.split_off(nonce_pos);
+        self.0
+            .decrypt_in_place(Nonce::from_slice(&nonce), &seq.to_le_bytes(), record)
+            .map_err(|_| failed())
+    }
+}
+
+impl fmt::Debug for SpoolCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SpoolCipher(AES-256-GCM)")
+    }
+}
+
+/// Returns the length of the authenticated header of an encrypted state.
+fn header_len(state: &[u8]) -> Result<usize, RotBufError> {
+    let too_short = RotBufError::InvalidState("encrypted state is shorter than its header");
+    let header_len = match state.get(..MAGIC.len()).ok_or(too_short.clone())? {
+        magic if magic == MAGIC => {
+            let mut version = state
+                .get(MAGIC.len()..MAGIC.len() + 2)
+                .ok_or(too_short.clone())?;
+            if version.get_u16_le() != ENVELOPE_VERSION {
+                return Err(RotBufError::InvalidState(
+                    "encrypted state was written by an unsupported version",
+                ));
+            }
+            MAGIC.len() + 2 + NONCE_LEN
+        }
+        _ => {
+            return Err(RotBufError::InvalidState(
+                "encrypted state does not start with the expected magic",
+            ))
+        }
+    };
+    if state.len() < header_len {
+        return Err(too_short);
+    }
+    Ok(header_len)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    const KEY: [u8; 32] = [7; 32];
+
+    #[test]
+    fn test_encrypted_round_trip() {
+        let mut rb = RotatingBuffer::try_new(5).unwrap();
+        rb.enqueue(10).unwrap();
+        rb.enqueue(20).unwrap();
+
+        let state = rb.export_state_encrypted(&KEY);
+        let mut imported = RotatingBuffer::import_state_encrypted(&state, &KEY).unwrap();
+        assert_eq!(imported.dequeue(), Some(10));
+        assert_eq!(imported.dequeue(), Some(20));
+    }
+
+    #[test]
+    fn test_encrypted_rejects_wrong_key_and_tampering() {
+        let mut rb = RotatingBuffer::try_new(5).unwrap();
+        rb.enqueue(10).unwrap();
+        let state = rb.export_state_encrypted(&KEY);
+
+        assert!(RotatingBuffer::import_state_encrypted(&state, &[8; 32]).is_err());
+
+        let mut tampered = state.to_vec();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(RotatingBuffer::import_state_encrypted(&tampered, &KEY).is_err());
+
+        let mut tampered_header = state.to_vec();
+        tampered_header[MAGIC.len()] ^= 1;
+        assert!(RotatingBuffer::import_state_encrypted(&tampered_header, &KEY).is_err());
+    }
+}
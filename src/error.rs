@@ -0,0 +1,256 @@
+//! Errors returned by the [RotatingBuffer] and its helpers.
+
+#[cfg(doc)]
+use crate::RotatingBuffer;
+
+/// [RotBufError] is the crate-wide error type.  Every error returned by this crate can be
+/// converted into a [RotBufError] (so `?` works across APIs), and it implements
+/// [std::error::Error] so it composes with other error handling crates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RotBufError {
+    /// The [RotatingBuffer] was at capacity.  Holds the value that could not be enqueued.
+    AtCapacity(u8),
+    /// The [RotatingBuffer] was empty.
+    Empty,
+    /// A position was outside of the queued bytes.
+    OutOfBounds {
+        /// The first position at or past the end of the queue that the operation would have
+        /// accessed.  For an operation on a single position, this is the requested position.
+        pos: usize,
+        /// The length of the queue at the time.
+        len: usize,
+    },
+    /// A [RotatingBuffer] cannot be created with this capacity.
+    InvalidCapacity(usize),
+    /// An exported state could not be imported, for the given reason.
+    InvalidState(&'static str),
+    /// A bulk operation only partially completed.
+    Incomplete {
+        /// How many bytes were processed before stopping.
+        written: usize,
+        /// How many bytes were left unprocessed.
+        remaining: usize,
+    },
+    /// The [RotatingBuffer] is poisoned, as an internal invariant was found to be broken.
+    Poisoned,
+    /// A frame header declared a payload longer than the [RotatingBuffer] can ever hold,
+    /// so the frame could never be dequeued.
+    FrameTooLarge {
+        /// The payload length declared by the header.
+        len: usize,
+        /// The longest payload the [RotatingBuffer] can hold.
+        max: usize,
+    },
+}
+
+impl std::fmt::Display for RotBufError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RotBufError::AtCapacity(value) => {
+                write!(f, "RotatingBuffer at capacity, returned input: `{}`", value)
+            }
+            RotBufError::Empty => write!(f, "RotatingBuffer is empty"),
+            RotBufError::OutOfBounds { pos, len } => write!(
+                f,
+                "Position `{}` is out of bounds for a RotatingBuffer of length `{}`",
+                pos, len
+            ),
+            RotBufError::InvalidCapacity(capacity) => write!(
+                f,
+                "Cannot create a RotatingBuffer with a capacity of `{}`",
+                capacity
+            ),
+            RotBufError::InvalidState(reason) => {
+                write!(f, "Invalid RotatingBuffer state: {}", reason)
+            }
+            RotBufError::Incomplete { written, remaining } => write!(
+                f,
+                "RotatingBuffer operation incomplete: `{}` bytes written, `{}` remaining",
+                written, remaining
+            ),
+            RotBufError::Poisoned => write!(
+                f,
+                "RotatingBuffer is poisoned after an internal invariant was broken"
+            ),
+            RotBufError::FrameTooLarge { len, max } => write!(
+                f,
+                "Frame payload of `{}` bytes can never fit in a RotatingBuffer holding at most `{}`",
+                len, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RotBufError {}
+
+/// Lets [RotBufError]s be propagated with `?` from functions returning [std::io::Error],
+/// such as the sources given to [RotatingBuffer::try_fill_slices_with].
+impl From<RotBufError> for std::io::Error {
+    fn from(err: RotBufError) -> Self {
+        std::io::Error::other(err)
+    }
+}
+
+/// [RotatingBufferAtCapacity] is a struct that represents an error.  It is returned whenever
+/// there was an attempt to enqueue a [RotatingBuffer] despite it being at capacity.  In
+/// this instance, the value given is returned to the user, and can be reclaimed using
+/// [RotatingBufferAtCapacity::reclaim], or inspected without consuming the error using
+/// [RotatingBufferAtCapacity::value].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RotatingBufferAtCapacity(pub(crate) u8);
+
+impl RotatingBufferAtCapacity {
+    /// Consumes the error, returning the inputted value.
+    pub fn reclaim(self) -> u8 {
+        self.0
+    }
+
+    /// Returns a reference to the inputted value.
+    pub fn value(&self) -> &u8 {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for RotatingBufferAtCapacity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "RotatingBuffer at capacity, returned input: `{}`",
+            self.0
+        )
+    }
+}
+
+/// [InvalidCapacity] is returned by [RotatingBuffer::try_new] when the requested size
+/// cannot be used for a [RotatingBuffer].  The rejected size can be retrieved using
+/// [InvalidCapacity::capacity].
+#[derive(Debug)]
+pub struct InvalidCapacity(pub(crate) usize);
+
+impl InvalidCapacity {
+    /// Returns the rejected size.
+    pub fn capacity(&self) -> usize {
+        self.0
+    }
+}
+
+impl std::fmt::Display for InvalidCapacity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cannot create a RotatingBuffer with a capacity of `{}`",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for RotatingBufferAtCapacity {}
+
+impl From<RotatingBufferAtCapacity> for RotBufError {
+    fn from(err: RotatingBufferAtCapacity) -> Self {
+        RotBufError::AtCapacity(err.0)
+    }
+}
+
+impl std::error::Error for InvalidCapacity {}
+
+impl From<InvalidCapacity> for RotBufError {
+    fn from(err: InvalidCapacity) -> Self {
+        RotBufError::InvalidCapacity(err.0)
+    }
+}
+
+/// [IncompleteEnqueue] is returned by bulk enqueue operations when the [RotatingBuffer]
+/// reached capacity before all of the input could be enqueued.  It records how many bytes
+/// were written and borrows the unwritten remainder, so the caller can resume exactly
+/// where the [RotatingBuffer] stopped them once there is room again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IncompleteEnqueue<'a> {
+    pub(crate) written: usize,
+    pub(crate) remainder: &'a [u8],
+}
+
+impl<'a> IncompleteEnqueue<'a> {
+    /// Returns how many bytes were enqueued before the [RotatingBuffer] was full.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Returns the bytes that were not enqueued.
+    pub fn remainder(&self) -> &'a [u8] {
+        self.remainder
+    }
+}
+
+impl std::fmt::Display for IncompleteEnqueue<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "RotatingBuffer at capacity after enqueueing `{}` bytes, `{}` bytes remain",
+            self.written,
+            self.remainder.len()
+        )
+    }
+}
+
+impl std::error::Error for IncompleteEnqueue<'_> {}
+
+impl From<IncompleteEnqueue<'_>> for RotBufError {
+    fn from(err: IncompleteEnqueue<'_>) -> Self {
+        RotBufError::Incomplete {
+            written: err.written,
+            remaining: err.remainder.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::RotatingBuffer;
+
+    fn enqueue_twice(rb: &mut RotatingBuffer) -> Result<(), RotBufError> {
+        rb.enqueue(1)?;
+        rb.enqueue(2)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors_convert_with_question_mark() {
+        let mut rb = RotatingBuffer::try_new(1).unwrap();
+        assert_eq!(enqueue_twice(&mut rb), Err(RotBufError::AtCapacity(2)));
+
+        let err: Box<dyn std::error::Error> = RotatingBuffer::try_new(0).unwrap_err().into();
+        assert_eq!(
+            err.to_string(),
+            "Cannot create a RotatingBuffer with a capacity of `0`"
+        );
+    }
+
+    #[test]
+    fn test_reclaim_at_capacity() {
+        let mut rb = RotatingBuffer::try_new(1).unwrap();
+        rb.enqueue(1).unwrap();
+        let err = rb.enqueue(2).unwrap_err();
+        assert_eq!(*err.value(), 2);
+        assert_eq!(err.reclaim(), 2);
+    }
+
+    #[test]
+    fn test_incomplete_enqueue_converts() {
+        let err = IncompleteEnqueue {
+            written: 2,
+            remainder: &[3, 4, 5],
+        };
+        assert_eq!(err.remainder(), &[3, 4, 5]);
+        assert_eq!(
+            RotBufError::from(err),
+            RotBufError::Incomplete {
+                written: 2,
+                remaining: 3
+            }
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Threshold and interval triggered flushing of a [RotatingBuffer] into a sink.
+
+use std::io::{self, ErrorKind, Write};
+use std::time::{Duration, Instant};
+
+use crate::RotatingBuffer;
+
+/// The [Flusher] drains a [RotatingBuffer] into a sink whenever the buffer's occupancy
+/// exceeds a threshold, or when an interval has elapsed since the last flush.
+///
+/// The [Flusher] never blocks waiting for either condition.  Synchronous callers invoke
+/// [Flusher::maybe_flush] after enqueueing (or on every loop iteration), and asynchronous
+/// callers additionally sleep until [Flusher::next_deadline] so interval flushes happen
+/// even when no new data arrives.
+#[derive(Debug)]
+pub struct Flusher<W> {
+    sink: W,
+    threshold: usize,
+    interval: Duration,
+    /// When the buffer was last flushed (or first observed).
+    last_flush: Option<Instant>,
+}
+
+impl<W: Write> Flusher<W> {
+    /// Creates a new [Flusher] writing into `sink` once more than `threshold` bytes are
+    /// queued, or once `interval` has elapsed since the previous flush.
+    pub fn new(sink: W, threshold: usize, interval: Duration) -> Self {
+        Self {
+            sink,
+            threshold,
+            interval,
+            last_flush: None,
+        }
+    }
+
+    /// Returns a reference to the sink.
+    pub fn sink(&self) -> &W {
+        &self.sink
+    }
+
+    /// Returns a mutable reference to the sink.
+    pub fn sink_mut(&mut self) -> &mut W {
+        &mut self.sink
+    }
+
+    /// Consumes the [Flusher], returning the sink.
+    pub fn into_inner(self) -> W {
+        self.sink
+    }
+
+    /// Returns when the next interval flush is due, or [None] if nothing has been observed
+    /// yet.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.last_flush.map(|last| last + self.interval)
+    }
+
+    /// Returns whether `rb` should be flushed at `now`.
+    pub fn should_flush(&self, rb: &RotatingBuffer, now: Instant) -> bool {
+        if rb.len() > self.threshold {
+            return true;
+        }
+        match self.next_deadline() {
+            Some(deadline) => now >= deadline && !rb.is_empty(),
+            None => false,
+        }
+    }
+
+    /// Flushes `rb` if it is over the threshold or the interval has elapsed, returning the
+    /// number of bytes written.
+    pub fn maybe_flush(&mut self, rb: &mut RotatingBuffer, now: Instant) -> io::Result<usize> {
+        if self.should_flush(rb, now) {
+            self.flush(rb, now)
+        } else {
+            if self.last_flush.is_none() {
+                self.last_flush = Some(now);
+            }
+            Ok(0)
+        }
+    }
+
+    /// Unconditionally writes every queued byte of `rb` into the sink and flushes the sink,
+    /// returning the number of bytes written.
+    ///
+    /// If the sink returns `WouldBlock`, the bytes it did accept are dequeued and the rest
+    /// are left for the next flush.
+    pub fn flush(&mut self, rb: &mut RotatingBuffer, now: Instant) -> io::Result<usize> {
+        let mut written = 0;
+        while !rb.is_empty() {
+            let (queued, _) = rb.segments();
+            match self.sink.write(queued) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    rb.consume_head(n);
+                    written += n;
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+        self.last_flush = Some(now);
+        match self.sink.flush() {
+            Err(err) if err.kind() == ErrorKind::WouldBlock => Ok(written),
+            result => result.map(|_| written),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_flushes_over_threshold() {
+        let mut rb = RotatingBuffer::try_new(8).unwrap();
+        let mut flusher = Flusher::new(Vec::new(), 4, Duration::from_secs(60));
+        let now = Instant::now();
+
+        rb.enqueue_partial(&[1, 2, 3, 4]);
+        assert_eq!(flusher.maybe_flush(&mut rb, now).unwrap(), 0);
+        rb.enqueue(5).unwrap();
+        assert_eq!(flusher.maybe_flush(&mut rb, now).unwrap(), 5);
+        assert!(rb.is_empty());
+        assert_eq!(flusher.sink(), &vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_flushes_after_interval() {
+        let mut rb = RotatingBuffer::try_new(8).unwrap();
+        let mut flusher = Flusher::new(Vec::new(), 4, Duration::from_secs(1));
+        let start = Instant::now();
+
+        rb.enqueue(1).unwrap();
+        assert_eq!(flusher.maybe_flush(&mut rb, start).unwrap(), 0);
+        assert_eq!(
+            flusher.next_deadline(),
+            Some(start + Duration::from_secs(1))
+        );
+        let before = start + Duration::from_millis(999);
+        assert_eq!(flusher.maybe_flush(&mut rb, before).unwrap(), 0);
+        let after = start + Duration::from_secs(1);
+        assert_eq!(flusher.maybe_flush(&mut rb, after).unwrap(), 1);
+        assert_eq!(
+            flusher.next_deadline(),
+            Some(after + Duration::from_secs(1))
+        );
+    }
+}
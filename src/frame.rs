@@ -0,0 +1,363 @@
+//! Length-prefixed frames, optionally split into continuation chunks, in a [RotatingBuffer].
+//!
+//! Every frame is a [FRAME_HEADER_LEN] byte header followed by its payload.  The header is
+//! a flags byte, where [FRAME_CONTINUES] marks a chunk that is followed by more chunks of
+//! the same message, then the payload length as a big-endian `u32`.
+//!
+//! A header declaring a payload longer than the ring can hold (its capacity less
+//! [FRAME_HEADER_LEN]) can never be completed, e.g. because the stream is corrupt, so
+//! dequeueing it fails with [RotBufError::FrameTooLarge] and leaves it queued.
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{IncompleteEnqueue, RotBufError, RotatingBuffer};
+
+/// The length of the header preceding every frame's payload.
+pub const FRAME_HEADER_LEN: usize = 5;
+
+/// The header flag marking a chunk that more chunks of the same message follow.
+pub const FRAME_CONTINUES: u8 = 0x01;
+
+impl RotatingBuffer {
+    /// Returns the flags and payload length of the frame at the head, or [None] if its
+    /// header is not fully queued.
+    fn frame_header(&self) -> Result<Option<(u8, usize)>, RotBufError> {
+        self.check_poisoned()?;
+        let mut header = [0; FRAME_HEADER_LEN];
+        if self.peek_slice(0..FRAME_HEADER_LEN, &mut header).is_err() {
+            return Ok(None);
+        }
+        let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        let max = self.capacity().saturating_sub(FRAME_HEADER_LEN);
+        if len > max {
+            return Err(RotBufError::FrameTooLarge { len, max });
+        }
+        Ok(Some((header[0], len)))
+    }
+
+    /// Enqueues a header and as much of `payload` as fits in one chunk.
+    fn enqueue_chunk(&mut self, flags: u8, payload: &[u8]) {
+        let mut header = [flags, 0, 0, 0, 0];
+        header[1..].copy_from_slice(&(payload.len() as u32).to_be_bytes());
+        self.as_one_batch(|rb| {
+            rb.enqueue_reserved(&header);
+            rb.enqueue_reserved(payload);
+        });
+    }
+
+    /// Enqueues `payload` as a single frame.
+    ///
+    /// This is all-or-nothing: if the header and payload do not fit, nothing is enqueued
+    /// and an [Err] with [RotBufError::Incomplete] is returned.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if `payload` is longer than [u32::MAX] bytes.
+    pub fn enqueue_frame(&mut self, payload: &[u8]) -> Result<(), RotBufError> {
+        self.check_poisoned()?;
+        assert!(
+            u32::try_from(payload.len()).is_ok(),
+            "A frame payload cannot be longer than u32::MAX bytes"
+        );
+        let total = payload.len().saturating_add(FRAME_HEADER_LEN);
+        if total > self.remaining_capacity() {
+            return Err(RotBufError::Incomplete {
+                written: 0,
+                remaining: total,
+            });
+        }
+        self.enqueue_chunk(0, payload);
+        Ok(())
+    }
+
+    /// Dequeues the payload of the frame at the head, or returns [None], dequeueing
+    /// nothing, if it is not fully queued yet.
+    ///
+    /// Chunks written by [RotatingBuffer::enqueue_frame_chunked] are returned one at a
+    /// time; use a [FrameReassembler] to get whole messages back.
+    ///
+    /// Returns an [Err] with [RotBufError::FrameTooLarge], dequeueing nothing, if the frame
+    /// could never fit in the ring.
+    pub fn dequeue_frame(&mut self) -> Result<Option<Bytes>, RotBufError> {
+        Ok(self.dequeue_chunk()?.map(|(_, payload)| payload))
+    }
+
+    /// Dequeues the frame at the head as a [FrameRef] borrowing its payload in place, or
+    /// returns [None], dequeueing nothing, if it is not fully queued yet.
+    ///
+    /// Unlike [RotatingBuffer::dequeue_frame], the payload is not copied out of the ring;
+    /// the frame is removed from the queue when the [FrameRef] is dropped.  Fails like
+    /// [RotatingBuffer::dequeue_frame] if the frame could never fit in the ring.
+    pub fn dequeue_frame_ref(&mut self) -> Result<Option<FrameRef<'_>>, RotBufError> {
+        let Some((flags, len)) = self.frame_header()? else {
+            return Ok(None);
+        };
+        if self.len() - FRAME_HEADER_LEN < len {
+            return Ok(None);
+        }
+        Ok(Some(FrameRef {
+            rb: self,
+            flags,
+            len,
+        }))
+    }
+
+    /// Dequeues the chunk at the head along with its flags, if it is fully queued.
+    fn dequeue_chunk(&mut self) -> Result<Option<(u8, Bytes)>, RotBufError> {
+        let Some((flags, len)) = self.frame_header()? else {
+            return Ok(None);
+        };
+        if self.len() - FRAME_HEADER_LEN < len {
+            return Ok(None);
+        }
+        self.consume_head(FRAME_HEADER_LEN);
+        let payload = self
+            .dequeue_bytes(len)
+            .expect("the payload is fully queued");
+        Ok(Some((flags, payload)))
+    }
+
+    /// Enqueues `payload` as one or more chunks of at most `chunk_size` bytes each, every
+    /// chunk but the last flagged with [FRAME_CONTINUES].
+    ///
+    /// Chunks are also shrunk to fit the remaining capacity, so a payload larger than the
+    /// whole ring can be written as the consumer frees up space.  If the [RotatingBuffer]
+    /// fills up first, an [Err] with an [IncompleteEnqueue] is returned holding the rest of
+    /// the payload; passing that remainder to this method again continues the same
+    /// message.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if `chunk_size` is 0 or larger than [u32::MAX].
+    pub fn enqueue_frame_chunked<'a>(
+        &mut self,
+        payload: &'a [u8],
+        chunk_size: usize,
+    ) -> Result<(), IncompleteEnqueue<'a>> {
+        assert!(
+            chunk_size > 0 && u32::try_from(chunk_size).is_ok(),
+            "The chunk size must be between 1 and u32::MAX bytes, not {}",
+            chunk_size
+        );
+        let mut written = 0;
+        loop {
+            let rest = &payload[written..];
+            let room = self.remaining_capacity().saturating_sub(FRAME_HEADER_LEN);
+            let len = rest.len().min(chunk_size).min(room);
+            // Every chunk but an empty final one must carry at least one byte.
+            if self.is_poisoned()
+                || self.remaining_capacity() < FRAME_HEADER_LEN
+                || (len == 0 && !rest.is_empty())
+            {
+                return Err(IncompleteEnqueue {
+                    written,
+                    remainder: rest,
+                });
+            }
+            let last = len == rest.len();
+            self.enqueue_chunk(if last { 0 } else { FRAME_CONTINUES }, &rest[..len]);
+            written += len;
+            if last {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// A frame at the head of a [RotatingBuffer], borrowing its payload in place.  Created by
+/// [RotatingBuffer::dequeue_frame_ref].
+///
+/// The frame is dequeued when the [FrameRef] is dropped.
+#[derive(Debug)]
+pub struct FrameRef<'a> {
+    rb: &'a mut RotatingBuffer,
+    flags: u8,
+    len: usize,
+}
+
+impl FrameRef<'_> {
+    /// Returns the length of the payload.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether or not the payload is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns whether this is a chunk that more chunks of the same message follow (see
+    /// [RotatingBuffer::enqueue_frame_chunked]).
+    pub fn continues(&self) -> bool {
+        self.flags & FRAME_CONTINUES != 0
+    }
+
+    /// Returns the payload as (at most) two slices; the second is empty unless the payload
+    /// wraps around the end of the ring.
+    pub fn as_slices(&self) -> (&[u8], &[u8]) {
+        self.rb
+            .range_segments(FRAME_HEADER_LEN, FRAME_HEADER_LEN + self.len)
+    }
+
+    /// Copies the payload into a [Bytes].
+    pub fn to_bytes(&self) -> Bytes {
+        let (first, second) = self.as_slices();
+        let mut out = BytesMut::with_capacity(self.len);
+        out.put_slice(first);
+        out.put_slice(second);
+        out.freeze()
+    }
+}
+
+impl Drop for FrameRef<'_> {
+    fn drop(&mut self) {
+        self.rb.consume_head(FRAME_HEADER_LEN + self.len);
+    }
+}
+
+/// The [FrameReassembler] joins the chunks written by
+/// [RotatingBuffer::enqueue_frame_chunked] back into whole messages.
+///
+/// Chunks are dequeued as soon as they are fully queued, and held until the final chunk
+/// of their message arrives, so the ring is freed for the rest of a large message.
+#[derive(Debug, Default)]
+pub struct FrameReassembler {
+    partial: BytesMut,
+}
+
+impl FrameReassembler {
+    /// Creates a new, empty [FrameReassembler].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of payload bytes held from a message whose final chunk has not
+    /// arrived yet.
+    pub fn pending_len(&self) -> usize {
+        self.partial.len()
+    }
+
+    /// Dequeues every fully queued chunk from `rb` until a message is complete, returning
+    /// it.  Returns [None] if the chunks queued so far do not complete a message.
+    ///
+    /// Fails like [RotatingBuffer::dequeue_frame] if a chunk could never fit in `rb`, in
+    /// which case the chunks already held are kept.
+    pub fn pull(&mut self, rb: &mut RotatingBuffer) -> Result<Option<Bytes>, RotBufError> {
+        while let Some((flags, payload)) = rb.dequeue_chunk()? {
+            if flags & FRAME_CONTINUES == 0 {
+                if self.partial.is_empty() {
+                    return Ok(Some(payload));
+                }
+                self.partial.put(payload);
+                return Ok(Some(self.partial.split().freeze()));
+            }
+            self.partial.put(payload);
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_single_frames() {
+        let mut rb = RotatingBuffer::try_new(16).unwrap();
+        rb.enqueue_frame(b"hello").unwrap();
+        rb.enqueue_frame(b"").unwrap();
+        assert_eq!(
+            rb.enqueue_frame(b"x"),
+            Err(RotBufError::Incomplete {
+                written: 0,
+                remaining: 6
+            })
+        );
+        assert_eq!(
+            rb.dequeue_frame().unwrap(),
+            Some(Bytes::from_static(b"hello"))
+        );
+        assert_eq!(rb.dequeue_frame().unwrap(), Some(Bytes::new()));
+        assert_eq!(rb.dequeue_frame().unwrap(), None);
+
+        // A partially queued frame is left in place.
+        rb.enqueue_partial(&[0, 0, 0, 0, 2, 9]);
+        assert_eq!(rb.dequeue_frame().unwrap(), None);
+        rb.enqueue(8).unwrap();
+        assert_eq!(
+            rb.dequeue_frame().unwrap(),
+            Some(Bytes::from_static(&[9, 8]))
+        );
+    }
+
+    #[test]
+    fn test_chunked_message_larger_than_ring() {
+        let payload: Vec<u8> = (0..100).collect();
+        let mut rb = RotatingBuffer::try_new(16).unwrap();
+        let mut reassembler = FrameReassembler::new();
+        let mut rest = Some(&payload[..]);
+        let mut message = None;
+        while message.is_none() {
+            if let Some(bytes) = rest {
+                rest = rb
+                    .enqueue_frame_chunked(bytes, 8)
+                    .err()
+                    .map(|incomplete| incomplete.remainder());
+            }
+            message = reassembler.pull(&mut rb).unwrap();
+            assert!(message.is_some() || reassembler.pending_len() > 0);
+        }
+        assert_eq!(message.unwrap().to_vec(), payload);
+        assert_eq!(reassembler.pending_len(), 0);
+        assert!(rb.is_empty());
+
+        // Messages that fit in one chunk pass straight through.
+        rb.enqueue_frame_chunked(b"abc", 8).unwrap();
+        rb.enqueue_frame_chunked(b"", 8).unwrap();
+        assert_eq!(reassembler.pull(&mut rb).unwrap().unwrap().to_vec(), b"abc");
+        assert_eq!(reassembler.pull(&mut rb).unwrap().unwrap().to_vec(), b"");
+        assert_eq!(reassembler.pull(&mut rb).unwrap(), None);
+    }
+
+    #[test]
+    fn test_dequeue_frame_ref() {
+        let mut rb = RotatingBuffer::try_new(16).unwrap();
+        rb.enqueue_partial(&[0; 8]);
+        rb.consume_head(8);
+        rb.enqueue_frame(b"abcdef").unwrap(); // Wraps around
+        rb.enqueue_partial(&[FRAME_CONTINUES, 0, 0, 0]);
+
+        let frame = rb.dequeue_frame_ref().unwrap().unwrap();
+        assert_eq!((frame.len(), frame.continues()), (6, false));
+        assert_eq!(frame.as_slices(), (&b"abc"[..], &b"def"[..]));
+        assert_eq!(frame.to_bytes(), Bytes::from_static(b"abcdef"));
+        drop(frame);
+        assert_eq!(rb.len(), 4);
+
+        // A frame whose header is not fully queued is left in place.
+        assert!(rb.dequeue_frame_ref().unwrap().is_none());
+        rb.enqueue(0).unwrap();
+        let frame = rb.dequeue_frame_ref().unwrap().unwrap();
+        assert!(frame.is_empty() && frame.continues());
+        drop(frame);
+        assert!(rb.is_empty());
+    }
+
+    #[test]
+    fn test_frame_too_large_for_ring() {
+        let mut rb = RotatingBuffer::try_new(8).unwrap();
+        // A 3 byte payload still fits, so the frame is just not fully queued yet.
+        rb.enqueue_partial(&[0, 0, 0, 0, 3, 0, 0]);
+        assert_eq!(rb.dequeue_frame(), Ok(None));
+        rb.enqueue(0).unwrap();
+        assert_eq!(rb.dequeue_frame_ref().unwrap().unwrap().len(), 3);
+
+        let too_large = Err(RotBufError::FrameTooLarge { len: 4, max: 3 });
+        rb.enqueue_partial(&[FRAME_CONTINUES, 0, 0, 0, 4]);
+        assert_eq!(rb.dequeue_frame(), too_large);
+        assert!(rb.dequeue_frame_ref().is_err());
+        assert_eq!(FrameReassembler::new().pull(&mut rb), too_large);
+        assert_eq!(rb.len(), 5);
+    }
+}
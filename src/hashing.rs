@@ -0,0 +1,65 @@
+//! Streaming digests of the bytes enqueued to a [RotatingBuffer].
+
+use std::fmt;
+
+use digest::DynDigest;
+
+use crate::RotatingBuffer;
+
+/// A hasher attached to a [RotatingBuffer] by [RotatingBuffer::attach_digest].
+pub(crate) struct AttachedDigest(Box<dyn DynDigest + Send + Sync>);
+
+impl AttachedDigest {
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+}
+
+impl fmt::Debug for AttachedDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AttachedDigest({} bytes)", self.0.output_size())
+    }
+}
+
+impl RotatingBuffer {
+    /// Attaches `hasher` (e.g. a `sha2::Sha256`), which is then fed every byte enqueued
+    /// until [RotatingBuffer::finalize_digest] is called.  Any previously attached hasher
+    /// is discarded.
+    ///
+    /// Bytes already queued are not hashed, so attach the hasher before the first byte of
+    /// the stream is enqueued.
+    pub fn attach_digest<D: DynDigest + Send + Sync + 'static>(&mut self, hasher: D) {
+        self.digest = Some(AttachedDigest(Box::new(hasher)));
+    }
+
+    /// Detaches the hasher and returns the digest of every byte enqueued since it was
+    /// attached, or [None] if no hasher is attached.
+    pub fn finalize_digest(&mut self) -> Option<Box<[u8]>> {
+        self.digest.take().map(|digest| digest.0.finalize())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_sha256_of_enqueued_stream() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue(b'x').unwrap();
+        rb.attach_digest(Sha256::new());
+        rb.dequeue();
+
+        let data: Vec<u8> = (0..=255).cycle().take(999).collect();
+        for chunk in data.chunks(3) {
+            assert_eq!(rb.enqueue_partial(&chunk[..2]), 2);
+            rb.enqueue(chunk[2]).unwrap();
+            rb.consume_head(3);
+        }
+
+        assert_eq!(rb.finalize_digest().unwrap()[..], Sha256::digest(&data)[..]);
+        assert_eq!(rb.finalize_digest(), None);
+    }
+}
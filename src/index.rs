@@ -0,0 +1,155 @@
+//! Typed index arithmetic for the ring.
+//!
+//! Logical positions in the queue (0 is the head) are plain `usize`s, while physical
+//! indices into the backing buffer are [Index]es.  The only way to turn one into the
+//! other, or to move an [Index], is through the [Capacity] of the ring, which keeps every
+//! wrap-around explicit and free of overflow.
+
+use std::num::NonZeroUsize;
+
+/// The capacity of a ring.  Never 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Capacity(NonZeroUsize);
+
+/// A physical index into the backing buffer of a ring.  Only produced by [Capacity], so it
+/// is always less than the capacity it came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub(crate) struct Index(usize);
+
+impl Index {
+    /// The first index of the backing buffer.
+    pub(crate) const ZERO: Index = Index(0);
+
+    /// Returns the raw index, for slicing the backing buffer.
+    pub(crate) fn get(self) -> usize {
+        self.0
+    }
+
+    /// Creates an [Index] without checking it against a [Capacity], for building corrupted
+    /// states in tests.
+    #[cfg(test)]
+    pub(crate) fn unchecked(raw: usize) -> Self {
+        Index(raw)
+    }
+}
+
+impl Capacity {
+    /// Returns the [Capacity] for `size`, or [None] if `size` is 0.
+    pub(crate) fn new(size: usize) -> Option<Self> {
+        NonZeroUsize::new(size).map(Capacity)
+    }
+
+    /// Returns the capacity as a `usize`.
+    pub(crate) fn get(self) -> usize {
+        self.0.get()
+    }
+
+    /// Returns `raw` as an [Index], or [None] if it is out of range.
+    pub(crate) fn index(self, raw: usize) -> Option<Index> {
+        (raw < self.get()).then_some(Index(raw))
+    }
+
+    /// Returns the index `n` slots after `index`, wrapping around the end of the ring.
+    ///
+    /// ## PANICS
+    ///
+    /// Panics if `n` is greater than the capacity, as that would lap the ring.
+    pub(crate) fn add(self, index: Index, n: usize) -> Index {
+        let to_end = self.get() - index.0;
+        match n {
+            n if n < to_end => Index(index.0 + n),
+            n if n <= self.get() => Index(n - to_end),
+            n => panic!(
+                "Cannot advance by {} in a ring of capacity {}",
+                n,
+                self.get()
+            ),
+        }
+    }
+
+    /// Returns the index `n` slots before `index`, wrapping around the start of the ring.
+    ///
+    /// ## PANICS
+    ///
+    /// Panics if `n` is greater than the capacity, as that would lap the ring.
+    pub(crate) fn sub(self, index: Index, n: usize) -> Index {
+        match n {
+            n if n <= index.0 => Index(index.0 - n),
+            n if n <= self.get() => Index(self.get() - (n - index.0)),
+            n => panic!(
+                "Cannot rewind by {} in a ring of capacity {}",
+                n,
+                self.get()
+            ),
+        }
+    }
+
+    /// Returns how many slots lie between `from` and `to`, moving forward from `from` and
+    /// wrapping if needed.  Equal indices are 0 apart.
+    pub(crate) fn distance(self, from: Index, to: Index) -> usize {
+        if from <= to {
+            to.0 - from.0
+        } else {
+            (self.get() - from.0) + to.0
+        }
+    }
+
+    /// Returns the physical [Index] of the logical position `pos`, counted from `head`.
+    ///
+    /// ## PANICS
+    ///
+    /// Panics if `pos` is not less than the capacity.
+    pub(crate) fn index_of(self, head: Index, pos: usize) -> Index {
+        if pos >= self.get() {
+            panic!(
+                "Position {} is outside a ring of capacity {}",
+                pos,
+                self.get()
+            );
+        }
+        self.add(head, pos)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_add_and_sub_wrap() {
+        let cap = Capacity::new(5).unwrap();
+        let three = cap.index(3).unwrap();
+        assert_eq!(cap.add(three, 1).get(), 4);
+        assert_eq!(cap.add(three, 2).get(), 0);
+        assert_eq!(cap.add(three, 5).get(), 3);
+        assert_eq!(cap.sub(three, 3).get(), 0);
+        assert_eq!(cap.sub(three, 4).get(), 4);
+        assert_eq!(cap.sub(three, 5).get(), 3);
+    }
+
+    #[test]
+    fn test_distance() {
+        let cap = Capacity::new(5).unwrap();
+        let (one, four) = (cap.index(1).unwrap(), cap.index(4).unwrap());
+        assert_eq!(cap.distance(one, four), 3);
+        assert_eq!(cap.distance(four, one), 2);
+        assert_eq!(cap.distance(four, four), 0);
+    }
+
+    #[test]
+    fn test_large_capacity_does_not_overflow() {
+        let cap = Capacity::new(usize::MAX).unwrap();
+        let last = cap.index(usize::MAX - 1).unwrap();
+        assert_eq!(cap.add(last, 1), Index::ZERO);
+        assert_eq!(cap.sub(Index::ZERO, 1), last);
+        assert!(cap.index(usize::MAX).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot advance by 6 in a ring of capacity 5")]
+    fn test_add_past_capacity_panics() {
+        let cap = Capacity::new(5).unwrap();
+        cap.add(Index::ZERO, 6);
+    }
+}
@@ -0,0 +1,188 @@
+//! [std::io] trait implementations for [RotatingBuffer].
+
+use std::io::{self, ErrorKind, IoSlice, IoSliceMut, Read, Write};
+
+use crate::RotatingBuffer;
+
+impl RotatingBuffer {
+    /// Returns the queued bytes as two [IoSlice]s in queue order, as with
+    /// [RotatingBuffer::as_slices], for passing to [Write::write_vectored] so both segments
+    /// go out in a single call.  Dequeue the bytes written afterwards with
+    /// [RotatingBuffer::skip].
+    pub fn as_io_slices(&self) -> [IoSlice<'_>; 2] {
+        let (first, second) = self.segments();
+        [IoSlice::new(first), IoSlice::new(second)]
+    }
+
+    /// Returns the free space past the tail as two [IoSliceMut]s, up to
+    /// [RotatingBuffer::remaining_capacity] bytes in total, for passing to
+    /// [Read::read_vectored] so both segments are filled in a single call.
+    ///
+    /// Bytes read into them are not enqueued; use [RotatingBuffer::read_from], which also
+    /// enqueues what was read, or [bytes::BufMut::advance_mut].
+    pub fn free_io_slices_mut(&mut self) -> [IoSliceMut<'_>; 2] {
+        let (first, second) = self.free_segments_mut();
+        [IoSliceMut::new(first), IoSliceMut::new(second)]
+    }
+
+    /// Reads from `reader` straight into the free space past the tail, handing it both
+    /// free segments at once through [Read::read_vectored], and enqueues what it read.
+    /// Returns how many bytes were enqueued, retrying if the read is interrupted.
+    ///
+    /// As with [Read::read], `Ok(0)` means the reader reached its end, unless the ring has
+    /// no [RotatingBuffer::remaining_capacity] left, in which case the reader is not called
+    /// at all.
+    pub fn read_from<R: Read + ?Sized>(&mut self, reader: &mut R) -> io::Result<usize> {
+        if self.remaining_capacity() == 0 {
+            return Ok(0);
+        }
+        let mut bufs = self.free_io_slices_mut();
+        let read = loop {
+            match reader.read_vectored(&mut bufs) {
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                result => break result?,
+            }
+        };
+        self.commit_tail(read);
+        Ok(read)
+    }
+
+    /// Writes the queued bytes into `writer`, handing it both segments at once through
+    /// [Write::write_vectored], and dequeues as many as it accepted.  Returns how many bytes
+    /// were dequeued, retrying if the write is interrupted.
+    ///
+    /// Returns `Ok(0)` without calling the writer if nothing is queued, and an [Err] with
+    /// [ErrorKind::WriteZero] if the writer accepted none of the queued bytes.
+    pub fn write_to<W: Write + ?Sized>(&mut self, writer: &mut W) -> io::Result<usize> {
+        if self.is_empty() {
+            return Ok(0);
+        }
+        let bufs = self.as_io_slices();
+        let written = loop {
+            match writer.write_vectored(&bufs) {
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                result => break result?,
+            }
+        };
+        if written == 0 {
+            return Err(ErrorKind::WriteZero.into());
+        }
+        self.consume_head(written);
+        Ok(written)
+    }
+}
+
+/// Reads by dequeueing from the head, copying out at most two segments per call.  Returns
+/// `Ok(0)` once the queue is empty, which readers such as [io::copy] take as the end of
+/// the stream.
+impl Read for RotatingBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(self.dequeue_into_vectored(&mut [buf]))
+    }
+}
+
+/// Writes by enqueueing at the tail.  A write that does not fit is cut short, and once the
+/// ring is full writes fail with [ErrorKind::WouldBlock], so dequeue from it and retry.
+/// Since [Write::write_all] gives up on that error, size writes with
+/// [RotatingBuffer::remaining_capacity] when the whole message must be enqueued.
+impl Write for RotatingBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.enqueue_partial(buf) {
+            0 if !buf.is_empty() => Err(ErrorKind::WouldBlock.into()),
+            written => Ok(written),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        rb.enqueue_partial(&[0; 4]);
+        rb.consume_head(4);
+        rb.enqueue_partial(&[1, 2, 3, 4, 5]); // Wraps around
+
+        let mut buf = [0; 3];
+        assert_eq!(rb.read(&mut buf).unwrap(), 3);
+        assert_eq!(buf, [1, 2, 3]);
+
+        let mut rest = Vec::new();
+        assert_eq!(io::copy(&mut rb, &mut rest).unwrap(), 2);
+        assert_eq!(rest, vec![4, 5]);
+        assert_eq!(rb.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_write() {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        rb.enqueue_partial(&[0; 4]);
+        rb.consume_head(4);
+        assert_eq!(rb.write(&[1, 2, 3, 4]).unwrap(), 4); // Wraps around
+        write!(rb, "{}", 56).unwrap();
+        assert_eq!(rb.last_n(6).to_vec(), vec![1, 2, 3, 4, b'5', b'6']);
+        assert_eq!(rb.write(&[]).unwrap(), 0);
+        assert_eq!(rb.write(&[7]).unwrap_err().kind(), ErrorKind::WouldBlock);
+        rb.flush().unwrap();
+    }
+
+    #[test]
+    fn test_read_from() {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        rb.enqueue_partial(&[0; 4]);
+        rb.consume_head(4);
+        let mut reader = io::Cursor::new(vec![1, 2, 3, 4, 5, 6, 7]);
+        while rb.read_from(&mut reader).unwrap() > 0 {}
+        assert!(rb.at_capacity());
+        assert_eq!(rb.last_n(6).to_vec(), vec![1, 2, 3, 4, 5, 6]); // Wrapped around
+        assert_eq!(reader.position(), 6);
+
+        rb.consume_head(6);
+        assert_eq!(rb.read_from(&mut reader).unwrap(), 1);
+        assert_eq!(rb.read_from(&mut reader).unwrap(), 0);
+        assert_eq!(rb.peek(), Some(7));
+    }
+
+    #[test]
+    fn test_write_to() {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        rb.enqueue_partial(&[0; 4]);
+        rb.consume_head(4);
+        rb.enqueue_partial(&[1, 2, 3, 4, 5]); // Wraps around
+        let mut out = Vec::new();
+        assert_eq!(rb.write_to(&mut out).unwrap(), 5);
+        assert_eq!(out, vec![1, 2, 3, 4, 5]);
+        assert_eq!(rb.write_to(&mut out).unwrap(), 0);
+
+        rb.enqueue_partial(&[6, 7, 8]);
+        let mut full = [0; 2];
+        assert_eq!(rb.write_to(&mut &mut full[..]).unwrap(), 2);
+        assert_eq!(full, [6, 7]);
+        let err = rb.write_to(&mut &mut full[2..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::WriteZero);
+        assert_eq!(rb.peek(), Some(8));
+    }
+
+    #[test]
+    fn test_io_slices() {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        rb.enqueue_partial(&[0; 4]);
+        rb.consume_head(4);
+        rb.enqueue_partial(&[1, 2, 3]); // Wraps around
+        let [first, second] = rb.as_io_slices();
+        assert_eq!((&*first, &*second), (&[1, 2][..], &[3][..]));
+
+        let [first, second] = rb.free_io_slices_mut();
+        assert_eq!((first.len(), second.len()), (3, 0));
+        rb.consume_head(3);
+        let [first, second] = rb.free_io_slices_mut();
+        assert_eq!((first.len(), second.len()), (5, 1));
+    }
+}
@@ -0,0 +1,546 @@
+//! Iterator trait implementations for [RotatingBuffer].
+
+use std::borrow::Cow;
+use std::iter::{Copied, Rev};
+use std::slice;
+
+use crate::{RotBufError, RotatingBuffer};
+
+impl RotatingBuffer {
+    /// Returns an iterator over copies of the queued bytes, from oldest to newest, without
+    /// consuming them.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.iter_refs().copied())
+    }
+
+    /// Returns an iterator over copies of the queued bytes from newest to oldest, the same
+    /// as `iter().rev()`, for scanning the most recent bytes first.
+    pub fn iter_back(&self) -> Rev<Iter<'_>> {
+        self.iter().rev()
+    }
+
+    /// Returns an iterator over the queued bytes in chunks of `n` bytes, from oldest to
+    /// newest, for block-oriented consumers such as ciphers and hashes.  As with
+    /// [slice::chunks], the last chunk is shorter if the length is not a multiple of `n`.
+    ///
+    /// Chunks are borrowed, except for the one (if any) spanning the wrap around the end of
+    /// the backing storage, which is copied.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if `n` is 0.
+    pub fn chunks(&self, n: usize) -> Chunks<'_> {
+        assert!(n > 0, "The chunk size must be non-zero");
+        Chunks {
+            rb: self,
+            n,
+            pos: 0,
+        }
+    }
+
+    /// Returns an iterator over every overlapping window of `n` queued bytes, from oldest
+    /// to newest, as with [slice::windows].  Nothing is yielded if fewer than `n` bytes are
+    /// queued.
+    ///
+    /// Windows are borrowed, except for those spanning the wrap around the end of the
+    /// backing storage, which are copied.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if `n` is 0.
+    pub fn windows(&self, n: usize) -> Windows<'_> {
+        assert!(n > 0, "The window size must be non-zero");
+        Windows {
+            rb: self,
+            n,
+            pos: 0,
+        }
+    }
+
+    /// Returns an iterator over references to the queued bytes, from oldest to newest.
+    pub fn iter_refs(&self) -> IterRefs<'_> {
+        let (first, second) = self.segments();
+        IterRefs {
+            first: first.iter(),
+            second: second.iter(),
+        }
+    }
+
+    /// Enqueues bytes pulled from `iter` until it ends or the [RotatingBuffer] is at
+    /// capacity, returning how many were enqueued.
+    ///
+    /// Once full, no further items are pulled, so a producer can resume from
+    /// `iter.by_ref()` once space has been freed without losing anything.  Nothing is pulled
+    /// from a poisoned [RotatingBuffer] either (see [RotatingBuffer::is_poisoned]).
+    pub fn enqueue_iter<I: IntoIterator<Item = u8>>(&mut self, iter: I) -> usize {
+        if self.poisoned {
+            return 0;
+        }
+        let (first, second) = self.free_segments_mut();
+        let mut written = 0;
+        for (slot, value) in first.iter_mut().chain(second.iter_mut()).zip(iter) {
+            *slot = value;
+            written += 1;
+        }
+        self.commit_tail(written);
+        written
+    }
+
+    /// Enqueues bytes pulled from the fallible source `f` until it returns `Ok(None)` or the
+    /// [RotatingBuffer] is at capacity, returning how many were enqueued.
+    ///
+    /// If `f` fails, its error is returned, and the bytes it produced before failing stay
+    /// enqueued.  As with [RotatingBuffer::enqueue_iter], `f` is not called once full.
+    ///
+    /// Returns [RotBufError::Poisoned], converted into `E`, without calling `f` if the
+    /// [RotatingBuffer] is poisoned.
+    pub fn try_fill_with<E: From<RotBufError>>(
+        &mut self,
+        mut f: impl FnMut() -> Result<Option<u8>, E>,
+    ) -> Result<usize, E> {
+        self.check_poisoned()?;
+        let (first, second) = self.free_segments_mut();
+        let (mut written, mut result) = (0, Ok(()));
+        for slot in first.iter_mut().chain(second.iter_mut()) {
+            match f() {
+                Ok(Some(value)) => *slot = value,
+                Ok(None) => break,
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+            written += 1;
+        }
+        self.commit_tail(written);
+        result.map(|()| written)
+    }
+
+    /// Like [RotatingBuffer::try_fill_with], but `f` writes a batch of bytes at a time into
+    /// the free space it is given and returns how many it wrote, `Ok(0)` meaning it is
+    /// exhausted.  It is called until the [RotatingBuffer] is at capacity, once for each
+    /// free segment if it fills them.  A poisoned [RotatingBuffer] is reported in the same
+    /// way.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if `f` claims to have written more bytes than it was given room for.
+    pub fn try_fill_slices_with<E: From<RotBufError>>(
+        &mut self,
+        mut f: impl FnMut(&mut [u8]) -> Result<usize, E>,
+    ) -> Result<usize, E> {
+        self.check_poisoned()?;
+        let mut written = 0;
+        loop {
+            let (free, _) = self.free_segments_mut();
+            let room = free.len();
+            if room == 0 {
+                break;
+            }
+            let n = f(free)?;
+            assert!(
+                n <= room,
+                "The source wrote {} bytes into {} bytes of free space",
+                n,
+                room
+            );
+            if n == 0 {
+                break;
+            }
+            self.commit_tail(n);
+            written += n;
+        }
+        Ok(written)
+    }
+}
+
+/// An iterator over references to the queued bytes of a [RotatingBuffer].  Created by
+/// [RotatingBuffer::iter_refs].
+#[derive(Debug, Clone)]
+pub struct IterRefs<'a> {
+    /// The bytes before and after the wrap around the end of the ring.
+    first: slice::Iter<'a, u8>,
+    second: slice::Iter<'a, u8>,
+}
+
+impl<'a> Iterator for IterRefs<'a> {
+    type Item = &'a u8;
+
+    fn next(&mut self) -> Option<&'a u8> {
+        self.first.next().or_else(|| self.second.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.first.len() + self.second.len();
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for IterRefs<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.second.next_back().or_else(|| self.first.next_back())
+    }
+}
+
+impl ExactSizeIterator for IterRefs<'_> {}
+
+/// An iterator over copies of the queued bytes of a [RotatingBuffer].  Created by
+/// [RotatingBuffer::iter].
+#[derive(Debug, Clone)]
+pub struct Iter<'a>(Copied<IterRefs<'a>>);
+
+impl Iterator for Iter<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<u8> {
+        self.0.next_back()
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+/// An iterator over fixed-size chunks of the queued bytes of a [RotatingBuffer].  Created
+/// by [RotatingBuffer::chunks].
+#[derive(Debug, Clone)]
+pub struct Chunks<'a> {
+    rb: &'a RotatingBuffer,
+    n: usize,
+    /// The position of the start of the next chunk.
+    pos: usize,
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = Cow<'a, [u8]>;
+
+    fn next(&mut self) -> Option<Cow<'a, [u8]>> {
+        let len = self.rb.len();
+        if self.pos == len {
+            return None;
+        }
+        let end = self.pos + self.n.min(len - self.pos);
+        let chunk = match self.rb.range_segments(self.pos, end) {
+            (whole, []) => Cow::Borrowed(whole),
+            (first, second) => Cow::Owned([first, second].concat()),
+        };
+        self.pos = end;
+        Some(chunk)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.rb.len() - self.pos).div_ceil(self.n);
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Chunks<'_> {}
+
+/// An iterator over overlapping windows of the queued bytes of a [RotatingBuffer].
+/// Created by [RotatingBuffer::windows].
+#[derive(Debug, Clone)]
+pub struct Windows<'a> {
+    rb: &'a RotatingBuffer,
+    n: usize,
+    /// The position of the start of the next window.
+    pos: usize,
+}
+
+impl<'a> Iterator for Windows<'a> {
+    type Item = Cow<'a, [u8]>;
+
+    fn next(&mut self) -> Option<Cow<'a, [u8]>> {
+        let end = self.pos + self.n;
+        if end > self.rb.len() {
+            return None;
+        }
+        let window = match self.rb.range_segments(self.pos, end) {
+            (whole, []) => Cow::Borrowed(whole),
+            (first, second) => Cow::Owned([first, second].concat()),
+        };
+        self.pos += 1;
+        Some(window)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.rb.len() + 1).saturating_sub(self.pos + self.n);
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Windows<'_> {}
+
+/// Dequeues the bytes, from oldest to newest.
+impl IntoIterator for RotatingBuffer {
+    type Item = u8;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        IntoIter(self)
+    }
+}
+
+impl<'a> IntoIterator for &'a RotatingBuffer {
+    type Item = u8;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator dequeueing the bytes of a [RotatingBuffer] it owns.  Created by its
+/// [IntoIterator] implementation.
+#[derive(Debug)]
+pub struct IntoIter(RotatingBuffer);
+
+impl IntoIter {
+    /// Returns the [RotatingBuffer] holding the bytes not yet yielded.
+    pub fn into_inner(self) -> RotatingBuffer {
+        self.0
+    }
+}
+
+impl Iterator for IntoIter {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        self.0.dequeue()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.len();
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for IntoIter {
+    fn next_back(&mut self) -> Option<u8> {
+        let value = self.0.peek_last()?;
+        self.0.truncate_back(1);
+        Some(value)
+    }
+}
+
+impl ExactSizeIterator for IntoIter {}
+
+/// Enqueues bytes until the iterator ends or the [RotatingBuffer] is at capacity.
+///
+/// Once full, no further items are pulled from the iterator, so nothing is lost by
+/// extending from `iter.by_ref()`: whatever did not fit is left in `iter`.  Use
+/// [RotatingBuffer::enqueue_iter] to find out how many bytes were enqueued.
+///
+/// ```
+/// # use rotbuf::RotatingBuffer;
+/// let mut rb = RotatingBuffer::try_new(3).unwrap();
+/// let mut bytes = [1, 2, 3, 4, 5].into_iter();
+/// rb.extend(bytes.by_ref());
+/// assert!(rb.at_capacity());
+/// assert_eq!(bytes.collect::<Vec<_>>(), vec![4, 5]);
+/// ```
+impl Extend<u8> for RotatingBuffer {
+    fn extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
+        self.enqueue_iter(iter);
+    }
+}
+
+/// Enqueues copies of the bytes, with the same policy as [`Extend<u8>`].
+impl<'a> Extend<&'a u8> for RotatingBuffer {
+    fn extend<I: IntoIterator<Item = &'a u8>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+/// Collects every byte into a [RotatingBuffer] whose capacity is the number of bytes
+/// collected, and at least 1.  The bytes are gathered into a [Vec] first, which is sized
+/// from the iterator's length hint and grows as needed, so no byte is dropped however
+/// little the hint promises.
+impl FromIterator<u8> for RotatingBuffer {
+    fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Self {
+        let bytes: Vec<u8> = iter.into_iter().collect();
+        let mut rb = RotatingBuffer::with_size(bytes.len().max(1));
+        rb.enqueue_partial(&bytes);
+        rb
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_extend_stops_at_capacity() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[0, 0, 0]);
+        rb.consume_head(3);
+        let mut pulled = 0;
+        rb.extend((1..=6).inspect(|_| pulled += 1)); // Wraps around
+        assert_eq!(pulled, 4);
+        assert_eq!(rb.last_n(4).to_vec(), vec![1, 2, 3, 4]);
+
+        rb.consume_head(2);
+        rb.extend(&[7]);
+        rb.extend(Vec::<u8>::new());
+        assert_eq!(rb.last_n(3).to_vec(), vec![3, 4, 7]);
+    }
+
+    #[test]
+    fn test_collect() {
+        let rb: RotatingBuffer = (1..=5).collect();
+        assert_eq!(rb.capacity(), 5);
+        assert!(rb.at_capacity());
+
+        // Iterators with a lower bound of 0 are collected whole too.
+        let rb: RotatingBuffer = (1..=5).filter(|_| true).collect();
+        assert_eq!(rb.capacity(), 5);
+        assert_eq!(rb.last_n(5).to_vec(), vec![1, 2, 3, 4, 5]);
+        let rb: RotatingBuffer = (0..=255).cycle().take(1000).skip_while(|_| false).collect();
+        assert_eq!(rb.len(), 1000);
+        assert!(rb.at_capacity());
+
+        let rb: RotatingBuffer = std::iter::empty().collect();
+        assert_eq!(rb.capacity(), 1);
+        assert!(rb.is_empty());
+    }
+
+    #[test]
+    fn test_enqueue_iter_counts_items() {
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
+        let mut source = 1..=5;
+        assert_eq!(rb.enqueue_iter(source.by_ref()), 3);
+        assert_eq!(rb.enqueue_iter(source.by_ref()), 0);
+        rb.consume_head(2);
+        assert_eq!(rb.enqueue_iter(source.by_ref()), 2);
+        assert_eq!(source.next(), None);
+        assert_eq!(rb.last_n(3).to_vec(), vec![3, 4, 5]);
+
+        rb.consume_head(3);
+        rb.poisoned = true;
+        let mut source = 1..=5;
+        assert_eq!(rb.enqueue_iter(source.by_ref()), 0);
+        assert_eq!(source.next(), Some(1));
+    }
+
+    #[test]
+    fn test_try_fill_with() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        let corrupt = RotBufError::InvalidState("corrupt");
+        let mut source = [Ok(1), Ok(2), Err(corrupt.clone()), Ok(3)].into_iter();
+        let mut pull = || source.next().transpose();
+        assert_eq!(rb.try_fill_with(&mut pull), Err(corrupt));
+        assert_eq!(rb.try_fill_with(&mut pull), Ok(1));
+        assert_eq!(rb.last_n(4).to_vec(), vec![1, 2, 3]);
+
+        rb.enqueue_partial(&[4]);
+        let never = || -> Result<_, RotBufError> { panic!() };
+        assert_eq!(rb.try_fill_with(never), Ok(0));
+
+        rb.consume_head(4);
+        rb.poisoned = true;
+        assert_eq!(rb.try_fill_with(never), Err(RotBufError::Poisoned));
+        assert_eq!(
+            rb.try_fill_slices_with(|_| -> Result<_, RotBufError> { panic!() }),
+            Err(RotBufError::Poisoned)
+        );
+        assert!(rb.is_empty());
+    }
+
+    #[test]
+    fn test_try_fill_slices_with() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[0, 0, 0]);
+        rb.consume_head(3);
+        let mut data = &[1, 2, 3, 4, 5][..];
+        let mut read = |free: &mut [u8]| std::io::Read::read(&mut data, free);
+        assert_eq!(rb.try_fill_slices_with(&mut read).unwrap(), 4); // Wraps around
+        assert_eq!(rb.last_n(4).to_vec(), vec![1, 2, 3, 4]);
+        rb.consume_head(4);
+        assert_eq!(rb.try_fill_slices_with(&mut read).unwrap(), 1);
+        assert_eq!(
+            rb.try_fill_slices_with(|_| Err::<usize, _>(RotBufError::InvalidState("failed"))),
+            Err(RotBufError::InvalidState("failed"))
+        );
+        assert_eq!(rb.len(), 1);
+    }
+
+    #[test]
+    fn test_iter_across_wrap() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[0, 0, 1]);
+        rb.consume_head(2);
+        rb.enqueue_partial(&[2, 3, 4]); // Wraps around
+        assert_eq!(rb.iter().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert_eq!(rb.iter().rev().collect::<Vec<_>>(), vec![4, 3, 2, 1]);
+        assert_eq!(rb.iter_refs().copied().sum::<u8>(), 10);
+        assert!(rb.iter_back().eq(rb.iter().rev()));
+        assert_eq!(rb.iter_back().position(|value| value == 3), Some(1));
+
+        let mut iter = rb.iter();
+        assert_eq!(iter.len(), 4);
+        assert_eq!(iter.next_back(), Some(4));
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+        assert_eq!(iter.collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(rb.len(), 4);
+        assert_eq!(RotatingBuffer::try_new(2).unwrap().iter().next(), None);
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
+        rb.enqueue_partial(&[0, 1]);
+        rb.consume_head(1);
+        rb.enqueue_partial(&[2, 3]); // Wraps around
+        let mut sum = 0;
+        for value in &rb {
+            sum += value;
+        }
+        assert_eq!(sum, 6);
+
+        let mut iter = rb.into_iter();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.next_back(), Some(3));
+        let rest = iter.into_inner();
+        assert_eq!(rest.into_iter().collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[test]
+    fn test_chunks() {
+        let mut rb = RotatingBuffer::try_new(8).unwrap();
+        rb.enqueue_partial(&[0; 6]);
+        rb.consume_head(6);
+        rb.enqueue_partial(&[1, 2, 3, 4, 5, 6, 7]); // Wraps around
+        let chunks: Vec<_> = rb.chunks(3).collect();
+        assert_eq!(chunks, vec![&[1, 2, 3][..], &[4, 5, 6], &[7]]);
+        assert!(matches!(chunks[0], Cow::Owned(_)));
+        assert!(matches!(chunks[1], Cow::Borrowed(_)));
+        assert_eq!(rb.chunks(2).len(), 4);
+        assert_eq!(rb.chunks(7).count(), 1);
+        assert_eq!(RotatingBuffer::try_new(1).unwrap().chunks(1).next(), None);
+    }
+
+    #[test]
+    fn test_windows() {
+        let mut rb = RotatingBuffer::try_new(5).unwrap();
+        rb.enqueue_partial(&[0; 3]);
+        rb.consume_head(3);
+        rb.enqueue_partial(&[1, 2, 3, 4]); // Wraps around
+        let windows: Vec<_> = rb.windows(2).collect();
+        assert_eq!(windows, vec![&[1, 2][..], &[2, 3], &[3, 4]]);
+        assert!(matches!(windows[0], Cow::Borrowed(_)));
+        assert!(matches!(windows[1], Cow::Owned(_)));
+        assert_eq!(rb.windows(4).len(), 1);
+        assert_eq!(rb.windows(5).len(), 0);
+        assert_eq!(rb.windows(5).next(), None);
+    }
+}
@@ -1,6 +1,85 @@
 #![doc = include_str!("../README.md")]
 
-use bytes::{BufMut, BytesMut};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use budget::BudgetCharge;
+use crc::CrcState;
+use index::{Capacity, Index};
+
+mod autotune;
+mod batch;
+mod budget;
+mod buf;
+#[cfg(feature = "checked")]
+mod checked;
+#[cfg(any(feature = "flate2", feature = "zstd"))]
+mod compress;
+mod consumer;
+mod crc;
+mod cursor;
+#[cfg(feature = "subtle")]
+mod ct;
+mod drain;
+#[cfg(feature = "encryption")]
+mod encryption;
+mod error;
+mod flush;
+mod frame;
+#[cfg(feature = "digest")]
+mod hashing;
+mod index;
+mod io;
+mod iter;
+mod map;
+mod overflow;
+mod region;
+mod relay;
+mod replay;
+mod rle;
+#[cfg(feature = "zeroize")]
+mod scrub;
+mod seek;
+mod spill;
+mod state;
+mod throttle;
+mod timestamped;
+mod txn;
+mod validate;
+mod watchdog;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use autotune::{AutoTuner, ResizeDecision};
+pub use batch::Batches;
+pub use budget::MemoryBudget;
+#[cfg(feature = "checked")]
+pub use checked::CheckedBuffer;
+#[cfg(any(feature = "flate2", feature = "zstd"))]
+pub use compress::{CompressingWriter, DecompressingReader};
+pub use consumer::PeekConsumer;
+pub use crc::{CrcAlgorithm, StreamCrc};
+pub use cursor::RotBufCursor;
+pub use drain::Drain;
+pub use error::{IncompleteEnqueue, InvalidCapacity, RotBufError, RotatingBufferAtCapacity};
+pub use flush::Flusher;
+pub use frame::{FrameReassembler, FrameRef, FRAME_CONTINUES, FRAME_HEADER_LEN};
+pub use iter::{Chunks, IntoIter, Iter, IterRefs, Windows};
+pub use map::RotatingBufferMap;
+pub use overflow::OverflowPolicy;
+pub use region::RegionedBuffer;
+pub use relay::{PumpStats, Relay};
+pub use replay::ReplayBuffer;
+pub use rle::DrainRle;
+pub use seek::SeekBuffered;
+pub use spill::{SpillingBuffer, SpoolCompression};
+pub use state::STATE_FORMAT_VERSION;
+pub use throttle::Throttle;
+pub use timestamped::TimestampedBuffer;
+pub use txn::WriteTransaction;
+pub use validate::ValidationReport;
+pub use watchdog::Watchdog;
 
 /// The [RotatingBuffer] is a queue implementation wrapping a [BytesMut].  
 /// 
@@ -13,84 +92,125 @@ pub struct RotatingBuffer {
     /// The index of the head of the queue.  This represents the first value
     /// that is officially part of the Queue (and in most cases, not the first value
     /// of the buffer)
-    head: usize,
+    head: Index,
     /// The index of the tail.  This represents the first non-enqueued value
     /// in the buffer that can be overwritten when we enqueue.  
-    tail: usize,
+    tail: Index,
     /// The size of the queue.
-    size: usize,
+    size: Capacity,
     /// Whether or not we are at capacity.
     at_capacity: bool,
+    /// The total number of bytes ever enqueued.
+    enqueue_offset: u64,
+    /// The total number of bytes ever dequeued.
+    dequeue_offset: u64,
+    /// Whether an internal invariant was found broken (see [RotatingBuffer::is_poisoned]).
+    poisoned: bool,
+    /// Whether slots are overwritten with zeros as soon as their bytes are dequeued.
+    zero_on_dequeue: bool,
+    /// The name identifying this ring in diagnostics, if set.
+    label: Option<&'static str>,
+    /// Which data is lost when an enqueue does not fit.
+    overflow_policy: OverflowPolicy,
+    /// The running CRCs of the enqueued and dequeued streams, if enabled.
+    crc: Option<Box<CrcState>>,
+    /// The share of the attached [MemoryBudget], if any, charged for the queued bytes.
+    budget: Option<BudgetCharge>,
+    /// The lengths of the queued batches, oldest first, if batch tracking is enabled.
+    batches: Option<VecDeque<usize>>,
+    /// The hasher fed every enqueued byte, if attached.
+    #[cfg(feature = "digest")]
+    digest: Option<hashing::AttachedDigest>,
 }
 
 impl RotatingBuffer {
-    /// Provides a partial, and invalid default struct in order to
-    fn partial_default() -> Self {
-        Self {
-            buffer: BytesMut::new(),
-            head: 0,
-            tail: 0,
-            size: 0,
-            at_capacity: false,
-        }
-    }
-
     /// Creates a new RotatingBuffer
     ///
     /// # PANICS
     ///
-    /// Panics if the size is less than 2.
+    /// Panics if the size is 0.  Deprecated in favour of [RotatingBuffer::try_new], which
+    /// reports an invalid size (e.g. one that comes from configuration) as an error.
+    #[deprecated(note = "use try_new")]
     pub fn new(size: usize) -> Self {
-        if size <= 2 {
-            panic!("Cannot create a RotatingBuffer with 2 elements or less.");
+        Self::with_size(size)
+    }
+
+    /// Creates a new RotatingBuffer for the constructors built on it that, like
+    /// [RotatingBuffer::new], panic on an invalid size.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if the size is 0.
+    pub(crate) fn with_size(size: usize) -> Self {
+        match Self::try_new(size) {
+            Ok(rb) => rb,
+            Err(_) => panic!("Cannot create a RotatingBuffer with 0 elements."),
         }
+    }
+
+    /// Creates a new RotatingBuffer, returning an [Err] with an [InvalidCapacity] instead
+    /// of panicking if the size is 0.
+    pub fn try_new(size: usize) -> Result<Self, InvalidCapacity> {
+        let capacity = Capacity::new(size).ok_or(InvalidCapacity(size))?;
+
+        Ok(Self::with_buffer(BytesMut::with_capacity(size), capacity))
+    }
 
+    /// Creates an empty RotatingBuffer of the given capacity around `buffer`, which must
+    /// have room for `size` bytes so that it never reallocates.
+    fn with_buffer(buffer: BytesMut, size: Capacity) -> Self {
         Self {
-            buffer: BytesMut::with_capacity(size),
+            buffer,
+            head: Index::ZERO,
+            tail: Index::ZERO,
             size,
-            ..Self::partial_default()
+            at_capacity: false,
+            enqueue_offset: 0,
+            dequeue_offset: 0,
+            poisoned: false,
+            zero_on_dequeue: false,
+            label: None,
+            overflow_policy: OverflowPolicy::DropNewest,
+            crc: None,
+            budget: None,
+            batches: None,
+            #[cfg(feature = "digest")]
+            digest: None,
         }
     }
 
-    fn tail(&self) -> usize {
+    fn tail(&self) -> Index {
         self.tail
     }
 
-    fn last_indx(&self) -> Option<usize> {
+    fn last_indx(&self) -> Option<Index> {
         if !self.is_empty() {
-            Some(self.tail() - 1)
+            Some(self.size.sub(self.tail(), 1))
         } else {
             None
         }
     }
 
     /// Returns the head of the queue
-    fn head(&self) -> usize {
+    fn head(&self) -> Index {
         self.head
     }
 
-    /// Sets the head position in the queue
-    fn set_head(&mut self, head: usize) {
-        #[cfg(debug_assertions)]
-        if head >= self.size {
-            unreachable!("Head should always be less than the size")
-        }
+    /// Sets the head position in the queue.  [Index] can only be created from the
+    /// capacity, so it is always less than the size.
+    fn set_head(&mut self, head: Index) {
         self.head = head;
     }
 
-    /// Sets the tail position in the queue
-    fn set_tail(&mut self, tail: usize) {
-        #[cfg(debug_assertions)]
-        if tail >= self.size {
-            unreachable!("Tail should always be less than the size")
-        }
-
+    /// Sets the tail position in the queue.  [Index] can only be created from the
+    /// capacity, so it is always less than the size.
+    fn set_tail(&mut self, tail: Index) {
         self.tail = tail;
     }
 
     /// Returns the index of the first position of the Queue, or None
     /// if empty.
-    fn first_indx(&self) -> Option<usize> {
+    fn first_indx(&self) -> Option<Index> {
         if self.is_empty() {
             None
         } else {
@@ -99,13 +219,13 @@ impl RotatingBuffer {
     }
 
     /// Returns the index in the RotatingBuffer given the position
-    fn get_index(&self, pos: usize) -> usize {
-        (pos + self.head) % self.size
+    fn get_index(&self, pos: usize) -> Index {
+        self.size.index_of(self.head(), pos)
     }
 
     /// Returns a value from the index
-    fn get_from_index(&self, index: usize) -> Option<u8> {
-        self.buffer.get(index).copied()
+    fn get_from_index(&self, index: Index) -> Option<u8> {
+        self.buffer.get(index.get()).copied()
     }
 
     /// Increments the head.
@@ -113,15 +233,12 @@ impl RotatingBuffer {
     /// ## DEBUG PANIC
     /// With `debug_assertions`, will perform a check to make sure it is not equal to tail first.
     pub(crate) fn incr_head(&mut self) {
-        self.set_head((self.head + 1) % self.size);
+        self.set_head(self.size.add(self.head(), 1));
     }
 
     /// Returns the index one slot before the head
-    pub(crate) fn prev_head(&self) -> usize {
-        match self.head() {
-            0 => self.size - 1,
-            n => n - 1,
-        }
+    pub(crate) fn prev_head(&self) -> Index {
+        self.size.sub(self.head(), 1)
     }
 
     /// Increments the tail.
@@ -130,13 +247,352 @@ impl RotatingBuffer {
     /// 
     /// Although this should never be called when we are at capacity, if we are at capacity
     /// and the head and the tail are at the same position, we panic, as we will then
-    /// be overwriting data.
+    /// be overwriting data.  In release builds, the [RotatingBuffer] is poisoned instead.
     pub(crate) fn incr_tail(&mut self) {
-        #[cfg(debug_assertions)]
         if self.head() == self.tail() && self.at_capacity() {
-            unreachable!("Cannot increment tail as it is at the head (full capacity)");
+            return self.poison("Cannot increment tail as it is at the head (full capacity)");
+        }
+        self.set_tail(self.size.add(self.tail(), 1))
+    }
+
+    /// Returns the queued bytes as (at most) two slices in queue order.  The first slice
+    /// starts at the head, the second is the wrapped portion starting at index 0.
+    pub(crate) fn segments(&self) -> (&[u8], &[u8]) {
+        if self.is_empty() {
+            (&[], &[])
+        } else if self.head() < self.tail() {
+            (&self.buffer[self.head().get()..self.tail().get()], &[])
+        } else {
+            (
+                &self.buffer[self.head().get()..self.size.get()],
+                &self.buffer[..self.tail().get()],
+            )
+        }
+    }
+
+    /// Returns the queued bytes in the logical range `start..end` as (at most) two slices
+    /// in queue order.
+    ///
+    /// ## PANICS
+    ///
+    /// Panics if `start > end` or `end > len()`.
+    pub(crate) fn range_segments(&self, start: usize, end: usize) -> (&[u8], &[u8]) {
+        if start > end || end > self.len() {
+            panic!(
+                "Range {}..{} is out of bounds for a RotatingBuffer of length {}",
+                start,
+                end,
+                self.len()
+            );
+        }
+        let (first, second) = self.segments();
+        let split = first.len();
+        match (start, end) {
+            (start, end) if end <= split => (&first[start..end], &[]),
+            (start, end) if start >= split => (&second[start - split..end - split], &[]),
+            (start, end) => (&first[start..], &second[..end - split]),
+        }
+    }
+
+    /// Returns the queued bytes as (at most) two mutable slices in queue order, as in
+    /// [RotatingBuffer::segments].
+    pub(crate) fn segments_mut(&mut self) -> (&mut [u8], &mut [u8]) {
+        let (head, tail) = (self.head().get(), self.tail().get());
+        if self.is_empty() {
+            (&mut [], &mut [])
+        } else if head < tail {
+            (&mut self.buffer[head..tail], &mut [])
+        } else {
+            let (front, back) = self.buffer[..self.size.get()].split_at_mut(head);
+            (back, &mut front[..tail])
+        }
+    }
+
+    /// Returns the queued bytes in the logical range `start..end` as (at most) two mutable
+    /// slices in queue order.
+    ///
+    /// ## PANICS
+    ///
+    /// Panics if `start > end` or `end > len()`.
+    pub(crate) fn range_segments_mut(&mut self, start: usize, end: usize) -> (&mut [u8], &mut [u8]) {
+        if start > end || end > self.len() {
+            panic!(
+                "Range {}..{} is out of bounds for a RotatingBuffer of length {}",
+                start,
+                end,
+                self.len()
+            );
+        }
+        let (first, second) = self.segments_mut();
+        let split = first.len();
+        match (start, end) {
+            (start, end) if end <= split => (&mut first[start..end], &mut []),
+            (start, end) if start >= split => (&mut second[start - split..end - split], &mut []),
+            (start, end) => (&mut first[start..], &mut second[..end - split]),
+        }
+    }
+
+    /// Returns the free region of the buffer as (at most) two mutable slices, in the
+    /// order they would be written to by [RotatingBuffer::enqueue], limited to
+    /// [RotatingBuffer::remaining_capacity] bytes.
+    ///
+    /// The backing buffer is lazily zero-filled up to the full capacity the first time
+    /// this is called, so the free region is always initialized memory.
+    pub(crate) fn free_segments_mut(&mut self) -> (&mut [u8], &mut [u8]) {
+        let limit = self.remaining_capacity();
+        let (first, second) = self.free_slots_mut();
+        let first_len = first.len().min(limit);
+        let second_len = second.len().min(limit - first_len);
+        (&mut first[..first_len], &mut second[..second_len])
+    }
+
+    /// Returns every free slot as in [RotatingBuffer::free_segments_mut], regardless of
+    /// any budget.  For writes whose size was already checked against the remaining
+    /// capacity.
+    fn free_slots_mut(&mut self) -> (&mut [u8], &mut [u8]) {
+        if self.buffer.len() < self.size.get() {
+            self.buffer.resize(self.size.get(), 0);
+        }
+        if self.at_capacity() {
+            return (&mut [], &mut []);
+        }
+        let (head, tail) = (self.head().get(), self.tail().get());
+        if tail < head {
+            (&mut self.buffer[tail..head], &mut [])
+        } else {
+            let (front, back) = self.buffer.split_at_mut(tail);
+            (back, &mut front[..head])
+        }
+    }
+
+    /// Marks `n` bytes past the tail (written through [RotatingBuffer::free_segments_mut])
+    /// as enqueued.
+    pub(crate) fn commit_tail(&mut self, n: usize) {
+        if n > self.free_slots() {
+            return self.poison("Cannot commit more bytes than are free");
+        }
+        if n == 0 {
+            return;
+        }
+        self.set_tail(self.size.add(self.tail(), n));
+        if self.tail() == self.head() {
+            self.at_capacity = true;
+        }
+        self.observe_enqueued(n);
+    }
+
+    /// Copies as much of `src` as fits into the free region and enqueues it, returning
+    /// how many bytes were enqueued.
+    pub(crate) fn enqueue_partial(&mut self, src: &[u8]) -> usize {
+        let n = src.len().min(self.remaining_capacity());
+        self.enqueue_reserved(&src[..n])
+    }
+
+    /// Copies as much of `src` as physically fits and enqueues it, returning how many bytes
+    /// were enqueued.  For writes already checked against
+    /// [RotatingBuffer::remaining_capacity], so that a [MemoryBudget] shrinking on another
+    /// thread in the meantime cannot tear them.
+    pub(crate) fn enqueue_reserved(&mut self, src: &[u8]) -> usize {
+        self.enqueue_reserved_with(src.len(), |offset, dst| {
+            dst.copy_from_slice(&src[offset..offset + dst.len()])
+        })
+    }
+
+    /// Like [RotatingBuffer::enqueue_reserved], for sources of `len` bytes that are not
+    /// slices: `copy(offset, dst)` fills `dst` with the source bytes starting at `offset`,
+    /// and is called once per free segment written to.
+    pub(crate) fn enqueue_reserved_with(
+        &mut self,
+        len: usize,
+        mut copy: impl FnMut(usize, &mut [u8]),
+    ) -> usize {
+        if self.poisoned {
+            return 0;
+        }
+        let (first, second) = self.free_slots_mut();
+        let first_len = first.len().min(len);
+        if first_len > 0 {
+            copy(0, &mut first[..first_len]);
+        }
+        let second_len = second.len().min(len - first_len);
+        if second_len > 0 {
+            copy(first_len, &mut second[..second_len]);
+        }
+        self.commit_tail(first_len + second_len);
+        first_len + second_len
+    }
+
+    /// The body of [RotatingBuffer::enqueue_slice], for sources of `len` bytes that are not
+    /// slices (see [RotatingBuffer::enqueue_reserved_with]): applies the [OverflowPolicy],
+    /// then enqueues as many bytes as fit, returning how many that was.
+    pub(crate) fn enqueue_slice_with(
+        &mut self,
+        len: usize,
+        copy: impl FnMut(usize, &mut [u8]),
+    ) -> usize {
+        self.make_room(len);
+        let n = len.min(self.remaining_capacity());
+        self.enqueue_reserved_with(n, copy)
+    }
+
+    /// Feeds the last `n` queued bytes, which were just enqueued, to the CRC and digest.
+    fn observe_enqueued(&mut self, n: usize) {
+        self.enqueue_offset += n as u64;
+        self.record_batch(n);
+        if let Some(budget) = &mut self.budget {
+            budget.charge(n);
+        }
+        if let Some(mut crc) = self.crc.take() {
+            let len = self.len();
+            let (first, second) = self.range_segments(len - n, len);
+            crc.enqueued(first);
+            crc.enqueued(second);
+            self.crc = Some(crc);
+        }
+        #[cfg(feature = "digest")]
+        if let Some(mut digest) = self.digest.take() {
+            let len = self.len();
+            let (first, second) = self.range_segments(len - n, len);
+            digest.update(first);
+            digest.update(second);
+            self.digest = Some(digest);
+        }
+    }
+
+    /// Moves the head forward by `n` bytes, discarding them from the queue.
+    pub(crate) fn consume_head(&mut self, n: usize) {
+        if n > self.len() {
+            return self.poison("Cannot consume more bytes than are queued");
+        }
+        self.remove_range(0, n);
+    }
+
+    /// Removes the bytes at positions `start..end` from the queue, closing the gap by
+    /// moving whichever side of it is shorter.
+    pub(crate) fn remove_range(&mut self, start: usize, end: usize) {
+        let n = end - start;
+        if n == 0 {
+            return;
+        }
+        self.dequeue_offset += n as u64;
+        if let Some(budget) = &mut self.budget {
+            budget.release(n);
+        }
+        self.forget_batched(start, end);
+        if let Some(mut crc) = self.crc.take() {
+            let (first, second) = self.range_segments(start, end);
+            crc.dequeued(first);
+            crc.dequeued(second);
+            self.crc = Some(crc);
+        }
+        let len = self.len();
+        if start <= len - end {
+            for pos in (0..start).rev() {
+                let (from, to) = (self.get_index(pos), self.get_index(pos + n));
+                self.buffer[to.get()] = self.buffer[from.get()];
+            }
+            self.zero_vacated(0, n);
+            self.set_head(self.size.add(self.head(), n));
+        } else {
+            for pos in end..len {
+                let (from, to) = (self.get_index(pos), self.get_index(pos - n));
+                self.buffer[to.get()] = self.buffer[from.get()];
+            }
+            self.zero_vacated(len - n, len);
+            self.set_tail(self.size.sub(self.tail(), n));
+        }
+        self.at_capacity = false;
+    }
+
+    /// Zeroes the slots at positions `start..end` if [RotatingBuffer::set_zero_on_dequeue]
+    /// is enabled, as they are about to be vacated.
+    fn zero_vacated(&mut self, start: usize, end: usize) {
+        if self.zero_on_dequeue {
+            let (first, second) = self.range_segments_mut(start, end);
+            first.fill(0);
+            second.fill(0);
+        }
+    }
+
+    /// Sets whether slots are overwritten with zeros as soon as their bytes are dequeued
+    /// (or otherwise removed), so that sensitive data such as key material does not linger
+    /// in the ring after it has been consumed.  Disabled by default.
+    ///
+    /// This also zeroes the old allocation when [RotatingBuffer::resize] moves the queue
+    /// into a new one.
+    pub fn set_zero_on_dequeue(&mut self, enabled: bool) {
+        self.zero_on_dequeue = enabled;
+    }
+
+    /// Returns whether slots are zeroed on dequeue (see
+    /// [RotatingBuffer::set_zero_on_dequeue]).
+    pub fn zero_on_dequeue(&self) -> bool {
+        self.zero_on_dequeue
+    }
+
+    /// Names the [RotatingBuffer] (e.g. `"uplink-tx"`), so that when a service has many
+    /// rings, its [Debug](std::fmt::Debug) output, [ValidationReport]s and invariant
+    /// violation panics say which one they are about.
+    pub fn set_label(&mut self, label: &'static str) {
+        self.label = Some(label);
+    }
+
+    /// Returns the name given by [RotatingBuffer::set_label], if any.
+    pub fn label(&self) -> Option<&'static str> {
+        self.label
+    }
+
+    /// Dequeues every byte, keeping the allocation, and moves the head and tail back to the
+    /// start of the buffer.  This is O(1) unless CRCs or
+    /// [RotatingBuffer::set_zero_on_dequeue] are enabled, as those visit every byte.
+    ///
+    /// With the `zeroize` feature, the backing storage is also zeroed.
+    pub fn clear(&mut self) {
+        self.consume_head(self.len());
+        self.set_head(Index::ZERO);
+        self.set_tail(Index::ZERO);
+        #[cfg(feature = "zeroize")]
+        self.scrub();
+    }
+
+    /// Clears the [RotatingBuffer] (see [RotatingBuffer::clear]) and overwrites the whole
+    /// backing storage with zeros.
+    pub fn clear_and_zero(&mut self) {
+        self.clear();
+        #[cfg(not(feature = "zeroize"))]
+        self.buffer.fill(0);
+    }
+
+    /// Handles a broken internal invariant.  With `debug_assertions` this panics, so the bug
+    /// is caught in tests.  In release builds the [RotatingBuffer] is poisoned instead, so
+    /// the corruption is reported rather than silently propagating.
+    #[track_caller]
+    fn poison(&mut self, reason: &'static str) {
+        if cfg!(debug_assertions) {
+            match self.label {
+                Some(label) => unreachable!("RotatingBuffer `{}`: {}", label, reason),
+                None => unreachable!("{}", reason),
+            }
+        }
+        self.poisoned = true;
+    }
+
+    /// Returns whether the [RotatingBuffer] is poisoned, meaning an internal invariant was
+    /// found to be broken (which is always a bug in this crate).
+    ///
+    /// A poisoned [RotatingBuffer] refuses to enqueue or dequeue anything, and the methods
+    /// returning a [RotBufError] return [RotBufError::Poisoned].  Its contents can still be
+    /// inspected, e.g. through [RotatingBuffer::debug_validate].
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Returns an [Err] with [RotBufError::Poisoned] if the [RotatingBuffer] is poisoned.
+    fn check_poisoned(&self) -> Result<(), RotBufError> {
+        match self.poisoned {
+            true => Err(RotBufError::Poisoned),
+            false => Ok(()),
         }
-        self.set_tail((self.tail + 1) % self.size)
     }
 
     /// Returns whether or not the [RotatingBuffer] is empty
@@ -148,25 +604,80 @@ impl RotatingBuffer {
     /// before we can no longer enqueue anymore elements.  Once we reach this capacity, you must dequeue
     /// in order to fit into the [RotatingBuffer] without resizing.
     pub fn capacity(&self) -> usize {
-        self.size
+        self.size.get()
+    }
+
+    /// Returns how many more elements can be enqueued before the [RotatingBuffer] is at
+    /// capacity.  This is `capacity() - len()`, unless less than that is left of an
+    /// attached [MemoryBudget].
+    pub fn remaining_capacity(&self) -> usize {
+        match &self.budget {
+            Some(budget) => self.free_slots().min(budget.available()),
+            None => self.free_slots(),
+        }
+    }
+
+    /// Returns how many slots are not holding queued bytes, regardless of any budget.
+    fn free_slots(&self) -> usize {
+        self.size.get() - self.len()
+    }
+
+    /// Changes the capacity of the [RotatingBuffer] to `capacity`, keeping every queued
+    /// byte.  This reallocates the backing buffer and moves the queue to its start.
+    ///
+    /// Returns an [Err] with [RotBufError::InvalidCapacity] if `capacity` is 0 or smaller
+    /// than the number of bytes queued.
+    pub fn resize(&mut self, capacity: usize) -> Result<(), RotBufError> {
+        self.check_poisoned()?;
+        let len = self.len();
+        let size = match Capacity::new(capacity) {
+            Some(size) if capacity >= len => size,
+            _ => return Err(RotBufError::InvalidCapacity(capacity)),
+        };
+        let mut buffer = BytesMut::with_capacity(capacity);
+        let (first, second) = self.segments();
+        buffer.put_slice(first);
+        buffer.put_slice(second);
+        if self.zero_on_dequeue {
+            self.buffer.fill(0);
+        }
+        #[cfg(feature = "zeroize")]
+        self.scrub();
+        self.adopt_buffer(buffer, size);
+        Ok(())
+    }
+
+    /// Replaces the backing storage with `buffer`, which must already hold the queued bytes
+    /// at its start and have room for `size` bytes, and the capacity with `size`.
+    fn adopt_buffer(&mut self, buffer: BytesMut, size: Capacity) {
+        let len = self.len();
+        self.buffer = buffer;
+        self.head = Index::ZERO;
+        self.tail = size.add(Index::ZERO, len);
+        self.size = size;
+        self.at_capacity = len == size.get();
+    }
+
+    /// Returns the stream offset of the tail: the total number of bytes ever enqueued.
+    ///
+    /// Together with [RotatingBuffer::dequeue_offset], this translates positions in the
+    /// queue into positions in the stream; position `pos` holds stream byte
+    /// `dequeue_offset() + pos`.
+    pub fn enqueue_offset(&self) -> u64 {
+        self.enqueue_offset
+    }
+
+    /// Returns the stream offset of the head: the total number of bytes ever dequeued.
+    pub fn dequeue_offset(&self) -> u64 {
+        self.dequeue_offset
     }
 
     /// Returns the number of elements currently in the Queue.
     pub fn len(&self) -> usize {
-        match (self.tail(), self.head()) {
-            (tail, head) if tail > head => tail - head,
-            (tail, head) if tail < head => (self.size - head) + tail,
+        match self.size.distance(self.head(), self.tail()) {
             // If head is at tail, then we are either empty or full.
-            (tail, head) if tail == head => {
-                if self.at_capacity() {
-                    self.size
-                } else {
-                    0
-                }
-            }
-            (tail, head) => {
-                unreachable!("`tail` ({}) must by >, <, or == to `head` ({})", tail, head)
-            }
+            0 if self.at_capacity() => self.size.get(),
+            len => len,
         }
     }
 
@@ -177,13 +688,233 @@ impl RotatingBuffer {
     pub fn peek_pos(&self, pos: usize) -> Option<u8> {
         match (pos, self.len()) {
             (0, _) => self.peek(),
-            (pos, len) if pos == len - 1 => self.peek_last(),
+            (pos, len) if pos + 1 == len => self.peek_last(),
             (pos, len) if pos < len => self.get_from_index(self.get_index(pos)),
             (pos, len) if pos >= len => None,
             (pos, len) => unreachable!("`pos` ({}) must be < or >= to `len` ({}) always", pos, len),
         }
     }
 
+    /// Returns a reference to the byte at position `pos`, or [None] if `pos` is past the
+    /// end of the queue.
+    pub fn get(&self, pos: usize) -> Option<&u8> {
+        match pos < self.len() {
+            true => self.buffer.get(self.get_index(pos).get()),
+            false => None,
+        }
+    }
+
+    /// Returns a mutable reference to the byte at position `pos`, e.g. to patch a length
+    /// field in place, or [None] if `pos` is past the end of the queue.
+    pub fn get_mut(&mut self, pos: usize) -> Option<&mut u8> {
+        match pos < self.len() {
+            true => {
+                let index = self.get_index(pos);
+                self.buffer.get_mut(index.get())
+            }
+            false => None,
+        }
+    }
+
+    /// Peek the value stored at a given position, reporting why the peek failed.
+    ///
+    /// Returns an [Err] with [RotBufError::Empty] if the queue is empty, or
+    /// [RotBufError::OutOfBounds] if `pos` is past the end of a non-empty queue.
+    pub fn try_peek_pos(&self, pos: usize) -> Result<u8, RotBufError> {
+        self.check_poisoned()?;
+        match self.len() {
+            0 => Err(RotBufError::Empty),
+            len if pos >= len => Err(RotBufError::OutOfBounds { pos, len }),
+            _ => Ok(self.peek_pos(pos).expect("`pos` is within the queue")),
+        }
+    }
+
+    /// Overwrites already-enqueued bytes with `src`, starting at position `pos` of the
+    /// queue.  This is useful for backfilling a length prefix once the size of the payload
+    /// following it is known.
+    ///
+    /// Returns an [Err] with [RotBufError::OutOfBounds] (reporting the first position past
+    /// the end of the queue that would have been written) if `src` does not fit within the
+    /// queued bytes, in which case nothing is overwritten.
+    pub fn overwrite_pos(&mut self, pos: usize, src: &[u8]) -> Result<(), RotBufError> {
+        self.check_poisoned()?;
+        let len = self.len();
+        match pos.checked_add(src.len()) {
+            Some(end) if end <= len => {
+                let (first, second) = self.range_segments_mut(pos, end);
+                let (src_first, src_second) = src.split_at(first.len());
+                first.copy_from_slice(src_first);
+                second.copy_from_slice(src_second);
+                Ok(())
+            }
+            _ => Err(RotBufError::OutOfBounds { pos: pos.max(len), len }),
+        }
+    }
+
+    /// Peeks the first value in the queue, returning an [Err] with [RotBufError::Empty]
+    /// if the queue is empty.
+    pub fn try_peek(&self) -> Result<u8, RotBufError> {
+        self.check_poisoned()?;
+        self.peek().ok_or(RotBufError::Empty)
+    }
+
+    /// Peeks the last value in the queue, returning an [Err] with [RotBufError::Empty]
+    /// if the queue is empty.
+    pub fn try_peek_last(&self) -> Result<u8, RotBufError> {
+        self.check_poisoned()?;
+        self.peek_last().ok_or(RotBufError::Empty)
+    }
+
+    /// Returns a copy of the newest `n` bytes in queue order (oldest of them first).  If
+    /// fewer than `n` bytes are queued, all of them are returned.
+    pub fn last_n(&self, n: usize) -> Bytes {
+        let len = self.len();
+        let (first, second) = self.range_segments(len - n.min(len), len);
+        let mut out = BytesMut::with_capacity(first.len() + second.len());
+        out.put_slice(first);
+        out.put_slice(second);
+        out.freeze()
+    }
+
+    /// Copies every queued byte onto the back of `other`, leaving this queue untouched, and
+    /// returns how many were copied.  The bytes are copied segment to segment, without an
+    /// intermediate buffer.
+    ///
+    /// This is all-or-nothing: if they do not all fit, nothing is copied and an [Err] with
+    /// [RotBufError::Incomplete] is returned.  It is also an [Err] with
+    /// [RotBufError::Poisoned] if either [RotatingBuffer] is poisoned.
+    pub fn fork_into(&self, other: &mut RotatingBuffer) -> Result<usize, RotBufError> {
+        self.check_poisoned()?;
+        let (first, second) = self.segments();
+        other.enqueue_vectored(&[first, second])
+    }
+
+    /// Returns the queued bytes as two slices in queue order, as with
+    /// [std::collections::VecDeque::as_slices].  The first starts at the head, and the
+    /// second holds the bytes that wrapped around to the start of the ring, and is empty if
+    /// none did.
+    pub fn as_slices(&self) -> (&[u8], &[u8]) {
+        self.segments()
+    }
+
+    /// Calls `f` with the queued bytes as two slices in queue order, as returned by
+    /// [RotatingBuffer::as_slices], and returns its result.  Handy for checksums, hashes
+    /// and vectored writes that only need the bytes for the duration of a call.
+    pub fn with_slices<R>(&self, f: impl FnOnce(&[u8], &[u8]) -> R) -> R {
+        let (first, second) = self.segments();
+        f(first, second)
+    }
+
+    /// Returns whether the queued bytes wrap around the end of the backing storage, i.e.
+    /// whether [RotatingBuffer::make_contiguous] would have to move anything.
+    pub fn is_wrapped(&self) -> bool {
+        self.second_segment_len() > 0
+    }
+
+    /// Returns the length of the first slice of [RotatingBuffer::as_slices]: the queued
+    /// bytes from the head up to the end of the backing storage, or to the tail.
+    pub fn first_segment_len(&self) -> usize {
+        self.len() - self.second_segment_len()
+    }
+
+    /// Returns the length of the second slice of [RotatingBuffer::as_slices]: the queued
+    /// bytes that wrapped around to the start of the backing storage.
+    pub fn second_segment_len(&self) -> usize {
+        match self.is_empty() || self.head() < self.tail() {
+            true => 0,
+            false => self.tail().get(),
+        }
+    }
+
+    /// Returns how many bytes can be enqueued before the write would wrap around the end of
+    /// the backing storage: the length of the first free region a reserve/commit style
+    /// writer (or [bytes::BufMut::chunk_mut]) gets.  Never more than
+    /// [RotatingBuffer::remaining_capacity].
+    pub fn contiguous_free_len(&self) -> usize {
+        let (head, tail) = (self.head().get(), self.tail().get());
+        let contiguous = match self.at_capacity() {
+            true => 0,
+            false if tail < head => head - tail,
+            false => self.size.get() - tail,
+        };
+        contiguous.min(self.remaining_capacity())
+    }
+
+    /// Rearranges the backing storage so the queued bytes are contiguous, and returns them
+    /// as a single mutable slice in queue order.
+    ///
+    /// Nothing is moved if the queue does not wrap.  Otherwise, when the free space can
+    /// hold the shorter side of the wrap, each queued byte is moved once; only a nearly
+    /// full ring falls back to rotating the whole storage in place.  Moving leaves stale
+    /// copies in the free slots, so those are zeroed if
+    /// [RotatingBuffer::set_zero_on_dequeue] or the `zeroize` feature is enabled.
+    pub fn make_contiguous(&mut self) -> &mut [u8] {
+        let len = self.len();
+        let (head, tail, cap) = (self.head().get(), self.tail().get(), self.size.get());
+        if len > 0 && head >= tail {
+            let (head_len, free) = (cap - head, cap - len);
+            if free >= head_len {
+                // Slide the wrapped bytes right, then bring the head segment to the start.
+                self.buffer.copy_within(..tail, head_len);
+                self.buffer.copy_within(head..cap, 0);
+                self.set_head(Index::ZERO);
+            } else if free >= tail {
+                // Slide the head segment left, then append the wrapped bytes after it.
+                self.buffer.copy_within(head..cap, head - tail);
+                self.buffer.copy_within(..tail, cap - tail);
+                self.set_head(self.size.sub(self.head(), tail));
+            } else {
+                self.buffer.rotate_left(head);
+                self.set_head(Index::ZERO);
+            }
+            self.set_tail(self.size.add(self.head(), len));
+            if self.zero_on_dequeue || cfg!(feature = "zeroize") {
+                let (first, second) = self.free_slots_mut();
+                first.fill(0);
+                second.fill(0);
+            }
+        }
+        let start = self.head().get();
+        &mut self.buffer[start..start + len]
+    }
+
+    /// Borrows the oldest `n` bytes without copying or consuming them, as (at most) two
+    /// slices in queue order; the second is empty unless the bytes wrap around the end of
+    /// the ring.  If fewer than `n` bytes are queued, all of them are returned.
+    pub fn peek_up_to(&self, n: usize) -> (&[u8], &[u8]) {
+        self.range_segments(0, n.min(self.len()))
+    }
+
+    /// Copies the bytes at positions `range` into `dst` without dequeueing them, e.g. to
+    /// inspect a multi-byte header.
+    ///
+    /// Returns an [Err] with [RotBufError::OutOfBounds] (reporting the first position past
+    /// the end of the queue that would have been read) if `range` is not within the queued
+    /// bytes, in which case `dst` is left untouched.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if `dst` is not the same length as `range`.
+    pub fn peek_slice(&self, range: Range<usize>, dst: &mut [u8]) -> Result<(), RotBufError> {
+        self.check_poisoned()?;
+        let len = self.len();
+        if range.start > range.end || range.end > len {
+            return Err(RotBufError::OutOfBounds {
+                pos: range.start.max(len),
+                len,
+            });
+        }
+        assert_eq!(
+            dst.len(),
+            range.len(),
+            "The destination must be as long as the peeked range"
+        );
+        let (first, second) = self.range_segments(range.start, range.end);
+        dst[..first.len()].copy_from_slice(first);
+        dst[first.len()..].copy_from_slice(second);
+        Ok(())
+    }
+
     /// Peeks the first value in the queue.  Returns [None] if the queue is empty.
     /// 
     /// This method should be preferred over calling [RotatingBuffer::peek_pos] at position 0.
@@ -204,18 +935,180 @@ impl RotatingBuffer {
     /// This should be fairly cheap to run, as no memory in the buffer is altered.  Once an item
     /// is dequeued, every sequential item's position is one less than it was before.
     pub fn dequeue(&mut self) -> Option<u8> {
+        if self.poisoned {
+            return None;
+        }
         match self.get_from_index(self.first_indx()?) {
             Some(value) => {
+                if self.zero_on_dequeue {
+                    let head = self.head();
+                    self.set_value(head, 0);
+                }
                 // Increment the head
                 self.incr_head();
                 // Make sure at_capacity is false, because if it was true, we just cleared it.
                 self.at_capacity = false;
+                self.dequeue_offset += 1;
+                if let Some(budget) = &mut self.budget {
+                    budget.release(1);
+                }
+                self.forget_batched(0, 1);
+                if let Some(crc) = &mut self.crc {
+                    crc.dequeued(&[value]);
+                }
                 Some(value)
             }
             None => {
-                unreachable!("If not empty, should be able to dequeue");
+                self.poison("If not empty, should be able to dequeue");
+                None
+            }
+        }
+    }
+
+    /// Discards the front `n` bytes without reading them.
+    ///
+    /// Returns an [Err] with [RotBufError::OutOfBounds] (reporting the first position past
+    /// the end of the queue that would have been discarded), discarding nothing, if fewer
+    /// than `n` bytes are queued.
+    pub fn advance(&mut self, n: usize) -> Result<(), RotBufError> {
+        self.check_poisoned()?;
+        match self.len() {
+            len if n > len => Err(RotBufError::OutOfBounds { pos: len, len }),
+            _ => {
+                self.consume_head(n);
+                Ok(())
+            }
+        }
+    }
+
+    /// Discards up to `n` bytes from the front without reading them, returning how many
+    /// were discarded.
+    pub fn skip(&mut self, n: usize) -> usize {
+        let n = n.min(self.len());
+        self.consume_head(n);
+        n
+    }
+
+    /// Removes up to `n` of the oldest bytes by moving the head forward, returning how many
+    /// were removed.  The same as [RotatingBuffer::skip].
+    pub fn truncate_front(&mut self, n: usize) -> usize {
+        let n = n.min(self.len());
+        self.remove_range(0, n);
+        n
+    }
+
+    /// Removes up to `n` of the newest bytes by moving the tail back, returning how many
+    /// were removed.  Useful to rewind bytes that were enqueued speculatively, such as a
+    /// read past the end of a frame.
+    ///
+    /// The removed bytes count as dequeued for [RotatingBuffer::dequeue_offset] and
+    /// [RotatingBuffer::crc], as with [RotatingBuffer::drain].
+    pub fn truncate_back(&mut self, n: usize) -> usize {
+        let len = self.len();
+        let n = n.min(len);
+        self.remove_range(len - n, len);
+        n
+    }
+
+    /// Removes and returns the byte at position `pos`, moving the newest byte into its
+    /// place.  This is O(1), but does not preserve the order of the remaining bytes.
+    /// Returns [None] if `pos` is past the end of the queue.
+    pub fn swap_remove_back(&mut self, pos: usize) -> Option<u8> {
+        let len = self.len();
+        if pos >= len {
+            return None;
+        }
+        let (index, last) = (self.get_index(pos), self.get_index(len - 1));
+        self.buffer.swap(index.get(), last.get());
+        let value = self.peek_last();
+        self.truncate_back(1);
+        value
+    }
+
+    /// Dequeues as many bytes as `dst` has room for into it, returning how many were
+    /// moved.  The bytes are copied with at most one [BufMut::put_slice] per segment.
+    pub fn dequeue_to_bufmut<B: BufMut + ?Sized>(&mut self, dst: &mut B) -> usize {
+        let n = self.len().min(dst.remaining_mut());
+        let (first, second) = self.range_segments(0, n);
+        dst.put_slice(first);
+        dst.put_slice(second);
+        self.consume_head(n);
+        n
+    }
+
+    /// Dequeues bytes into each slice of `dsts` in turn, filling one before moving on to
+    /// the next (e.g. a header buffer, then a body buffer), and returns the total number of
+    /// bytes dequeued.  Stops early once the queue is empty.
+    pub fn dequeue_into_vectored(&mut self, dsts: &mut [&mut [u8]]) -> usize {
+        let mut total = 0;
+        for dst in dsts.iter_mut() {
+            if self.is_empty() {
+                break;
+            }
+            let n = dst.len().min(self.len());
+            let (first, second) = self.range_segments(0, n);
+            dst[..first.len()].copy_from_slice(first);
+            dst[first.len()..n].copy_from_slice(second);
+            self.consume_head(n);
+            total += n;
+        }
+        total
+    }
+
+    /// Dequeues the front `n` bytes as a [Bytes].  Returns [None], dequeueing nothing, if
+    /// fewer than `n` bytes are queued.
+    ///
+    /// The ring keeps its fixed allocation for reuse, so the bytes are copied out into a
+    /// single allocation of exactly `n` bytes rather than split off the backing buffer.
+    pub fn dequeue_bytes(&mut self, n: usize) -> Option<Bytes> {
+        if n > self.len() {
+            return None;
+        }
+        let (first, second) = self.range_segments(0, n);
+        let bytes = match second {
+            [] => Bytes::copy_from_slice(first),
+            _ => {
+                let mut out = BytesMut::with_capacity(n);
+                out.put_slice(first);
+                out.put_slice(second);
+                out.freeze()
             }
+        };
+        self.consume_head(n);
+        Some(bytes)
+    }
+
+    /// Dequeues the front `n` bytes as a [Bytes] split off the backing buffer, rather than
+    /// copied out as [RotatingBuffer::dequeue_bytes] does.  Returns [None], dequeueing
+    /// nothing, if fewer than `n` bytes are queued.
+    ///
+    /// The ring gives up its allocation to the returned [Bytes] and moves the rest of the
+    /// queue into a new one, so this pays off when `n` is most of the queue, e.g. handing a
+    /// large message off to consumers that hold onto it.  If the bytes wrap around the end
+    /// of the backing storage, or slots are zeroed on dequeue (see
+    /// [RotatingBuffer::set_zero_on_dequeue] and the `zeroize` feature), they are copied
+    /// instead.
+    pub fn freeze_front(&mut self, n: usize) -> Option<Bytes> {
+        if n > self.len() {
+            return None;
+        }
+        let head = self.head().get();
+        let secret = self.zero_on_dequeue || cfg!(feature = "zeroize");
+        if n == 0 || secret || head + n > self.size.get() {
+            return self.dequeue_bytes(n);
         }
+        // Dequeueing from the front moves nothing, so the bytes stay where they are.
+        self.consume_head(n);
+        let mut buffer = BytesMut::with_capacity(self.size.get());
+        let (first, second) = self.segments();
+        buffer.put_slice(first);
+        buffer.put_slice(second);
+        let mut frozen = std::mem::replace(&mut self.buffer, buffer);
+        frozen.advance(head);
+        frozen.truncate(n);
+        self.head = Index::ZERO;
+        self.tail = self.size.add(Index::ZERO, self.buffer.len());
+        Some(frozen.freeze())
     }
 
     /// Sets the value at an index, not the queue position.  Can only set values from 0 
@@ -227,8 +1120,8 @@ impl RotatingBuffer {
     /// panic will occur.  The [RotatingBuffer] is a queue implementation, meaning there
     /// should never be a time we are writing further in the buffer than the most recent
     /// writing.
-    fn set_value(&mut self, index: usize, value: u8) {
-        match (index, self.buffer.len()) {
+    fn set_value(&mut self, index: Index, value: u8) {
+        match (index.get(), self.buffer.len()) {
             (index, len) if index == len => {
                 self.buffer.put_u8(value);
             }
@@ -254,53 +1147,160 @@ impl RotatingBuffer {
     }
 
     /// Enqueues an item into the [RotatingBuffer].  Returns an [Err] with a 
-    /// [RotatingBufferAtCapacity] if at capacity.
+    /// [RotatingBufferAtCapacity] if at capacity, unless the [OverflowPolicy] is to drop
+    /// the oldest byte instead.
     /// 
     /// Enqueueing should be fairly cheap, as we initialize the internal buffer 
     /// with the maximum size given in the constructor, so we will always be
     /// either replacing a pre-existing and already dequeued value, or we will
     /// be placing a value into already allocated memory.
     pub fn enqueue(&mut self, value: u8) -> Result<(), RotatingBufferAtCapacity> {
-        // If we are at capacity, return error, otherwise add tail
-        if self.at_capacity() {
+        self.make_room(1);
+        // If we are at capacity (or poisoned), return error, otherwise add tail
+        if self.at_capacity() || self.poisoned || self.remaining_capacity() == 0 {
             Err(RotatingBufferAtCapacity(value))
         } else {
             // Retrieve the tail at current state
             let tail = self.tail();
-            // If this is the last spot, then set the at_capacity boolean
-            if tail == self.prev_head() {
-                self.at_capacity = true;
-            }
             // Set the value and increment the tail.
             self.set_value(tail, value);
             self.incr_tail();
+            // If this was the last spot, then set the at_capacity boolean.  This is done
+            // after incrementing so a capacity of 1 (where the tail wraps straight back
+            // onto the head) isn't mistaken for overwriting data.
+            if tail == self.prev_head() {
+                self.at_capacity = true;
+            }
+            self.observe_enqueued(1);
             // Return okay
             Ok(())
         }
     }
+
+    /// Enqueues as much of `src` as fits, copying it in at most two `memcpy`s across the
+    /// wrap point, and returns the number of bytes written.
+    ///
+    /// If the [RotatingBuffer] fills up first, the bytes that fit are still enqueued and
+    /// an [Err] with an [IncompleteEnqueue] is returned, which borrows the unwritten
+    /// remainder of `src` so the caller can retry once there is room.  With
+    /// [OverflowPolicy::DropOldest], the oldest bytes are evicted to make room first.
+    pub fn enqueue_slice<'a>(&mut self, src: &'a [u8]) -> Result<usize, IncompleteEnqueue<'a>> {
+        let written = self.enqueue_slice_with(src.len(), |offset, dst| {
+            dst.copy_from_slice(&src[offset..offset + dst.len()])
+        });
+        match written {
+            written if written == src.len() => Ok(written),
+            written => Err(IncompleteEnqueue {
+                written,
+                remainder: &src[written..],
+            }),
+        }
+    }
+
+    /// Moves as many bytes as fit from `src` into the queue, advancing `src` past them, and
+    /// returns how many were moved.  Works with any [Buf], such as a [Bytes] or a chain of
+    /// them, without collecting it into a contiguous buffer first.
+    pub fn enqueue_buf<B: Buf + ?Sized>(&mut self, src: &mut B) -> usize {
+        let mut written = 0;
+        while src.has_remaining() {
+            let n = self.enqueue_partial(src.chunk());
+            if n == 0 {
+                break;
+            }
+            src.advance(n);
+            written += n;
+        }
+        written
+    }
+
+    /// Enqueues every slice in `bufs` back to back (e.g. a header, payload and trailer),
+    /// returning the total number of bytes enqueued.
+    ///
+    /// This is all-or-nothing: if the slices do not all fit, nothing is enqueued and an
+    /// [Err] with [RotBufError::Incomplete] is returned.
+    pub fn enqueue_vectored(&mut self, bufs: &[&[u8]]) -> Result<usize, RotBufError> {
+        self.check_poisoned()?;
+        let total = bufs
+            .iter()
+            .fold(0usize, |total, buf| total.saturating_add(buf.len()));
+        if total > self.remaining_capacity() {
+            return Err(RotBufError::Incomplete {
+                written: 0,
+                remaining: total,
+            });
+        }
+        self.as_one_batch(|rb| {
+            for buf in bufs {
+                rb.enqueue_reserved(buf);
+            }
+        });
+        Ok(total)
+    }
+
+    /// Enqueues `n` copies of `value`, such as silence or padding, filling the free space
+    /// a segment at a time rather than a byte at a time.
+    ///
+    /// This is all-or-nothing: if fewer than `n` slots are free, nothing is enqueued and an
+    /// [Err] with [RotBufError::Incomplete] is returned.
+    pub fn fill(&mut self, value: u8, n: usize) -> Result<(), RotBufError> {
+        self.check_poisoned()?;
+        if n > self.remaining_capacity() {
+            return Err(RotBufError::Incomplete {
+                written: 0,
+                remaining: n,
+            });
+        }
+        let (first, second) = self.free_slots_mut();
+        let first_len = first.len().min(n);
+        first[..first_len].fill(value);
+        second[..n - first_len].fill(value);
+        self.commit_tail(n);
+        Ok(())
+    }
 }
 
-/// [RotatingBufferAtCapacity] is a struct that represents an error.  It is returned whenever
-/// there was an attempt to enqueue a [RotatingBuffer] despite it being at capacity.  In
-/// this instance, the value given is returned to the user, and can be reclaimed using
-/// [RotatingBufferAtCapacity::reclaim].
-#[derive(Debug)]
-pub struct RotatingBufferAtCapacity(u8);
+/// Accesses the byte at a position in the queue.
+///
+/// # PANICS
+///
+/// Panics if the position is past the end of the queue; see [RotatingBuffer::get].
+impl std::ops::Index<usize> for RotatingBuffer {
+    type Output = u8;
+
+    fn index(&self, pos: usize) -> &u8 {
+        let len = self.len();
+        self.get(pos).unwrap_or_else(|| {
+            panic!("Position {} is out of bounds for a RotatingBuffer of length {}", pos, len)
+        })
+    }
+}
 
-impl RotatingBufferAtCapacity {
-    /// Returns the inputted value.
-    pub fn reclaim(&self) -> u8 {
-        self.0
+/// Mutably accesses the byte at a position in the queue.
+///
+/// # PANICS
+///
+/// Panics if the position is past the end of the queue; see [RotatingBuffer::get_mut].
+impl std::ops::IndexMut<usize> for RotatingBuffer {
+    fn index_mut(&mut self, pos: usize) -> &mut u8 {
+        let len = self.len();
+        self.get_mut(pos).unwrap_or_else(|| {
+            panic!("Position {} is out of bounds for a RotatingBuffer of length {}", pos, len)
+        })
     }
 }
 
-impl std::fmt::Display for RotatingBufferAtCapacity {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "RotatingBuffer at capacity, returned input: `{}`",
-            self.0
-        )
+/// Enqueues formatted text, such as log lines written with [write!].
+///
+/// Each string piece is enqueued whole or not at all, so a character is never split, and
+/// [std::fmt::Error] is returned for the first piece that does not fit.  A single [write!]
+/// may enqueue several pieces, so check [RotatingBuffer::remaining_capacity] first, or
+/// format into a [String], when a message must not be cut short.
+impl std::fmt::Write for RotatingBuffer {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        match self.enqueue_vectored(&[s.as_bytes()]) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(std::fmt::Error),
+        }
     }
 }
 
@@ -310,26 +1310,49 @@ mod test {
     use super::*;
 
     #[test]
-    #[should_panic(expected = "Cannot create a RotatingBuffer with 2 elements or less.")]
+    #[should_panic(expected = "Cannot create a RotatingBuffer with 0 elements.")]
+    #[allow(deprecated)]
     fn test_new_panics_empty() {
         let _rb = RotatingBuffer::new(0);
     }
 
     #[test]
-    #[should_panic(expected = "Cannot create a RotatingBuffer with 2 elements or less.")]
-    fn test_new_panics_with_small_size_1() {
-        let _rb = RotatingBuffer::new(1);
+    fn test_try_new() {
+        assert_eq!(RotatingBuffer::try_new(0).unwrap_err().capacity(), 0);
+        assert_eq!(RotatingBuffer::try_new(1).unwrap().capacity(), 1);
     }
 
     #[test]
-    #[should_panic(expected = "Cannot create a RotatingBuffer with 2 elements or less.")]
-    fn test_new_panics_with_small_size_2() {
-        let _rb = RotatingBuffer::new(2);
+    fn test_capacity_1() {
+        let mut rb = RotatingBuffer::try_new(1).unwrap();
+        rb.enqueue(1).unwrap();
+        assert!(rb.at_capacity());
+        assert_eq!(rb.len(), 1);
+        assert_eq!(rb.peek_last(), Some(1));
+        assert!(rb.enqueue(2).is_err());
+        assert_eq!(rb.dequeue(), Some(1));
+        assert!(rb.is_empty());
+        rb.enqueue(3).unwrap();
+        assert_eq!(rb.dequeue(), Some(3));
+    }
+
+    #[test]
+    fn test_capacity_2() {
+        let mut rb = RotatingBuffer::try_new(2).unwrap();
+        rb.enqueue(1).unwrap();
+        rb.enqueue(2).unwrap();
+        assert!(rb.at_capacity());
+        assert_eq!(rb.dequeue(), Some(1));
+        rb.enqueue(3).unwrap(); // Wraps around
+        assert_eq!(rb.peek_last(), Some(3));
+        assert_eq!(rb.dequeue(), Some(2));
+        assert_eq!(rb.dequeue(), Some(3));
+        assert!(rb.is_empty());
     }
 
     #[test]
     fn test_enqueue_dequeue() {
-        let mut rb = RotatingBuffer::new(3);
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
         rb.enqueue(1).unwrap();
         rb.enqueue(2).unwrap();
         assert_eq!(rb.dequeue(), Some(1));
@@ -339,7 +1362,7 @@ mod test {
 
     #[test]
     fn test_enqueue_at_capacity() {
-        let mut rb = RotatingBuffer::new(3);
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
         rb.enqueue(1).unwrap();
         rb.enqueue(2).unwrap();
         rb.enqueue(3).unwrap();
@@ -352,7 +1375,7 @@ mod test {
 
     #[test]
     fn test_peek_last_functions() {
-        let mut rb = RotatingBuffer::new(3);
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
         rb.enqueue(1).unwrap();
         rb.enqueue(2).unwrap();
         assert_eq!(rb.peek_last(), Some(2));
@@ -360,7 +1383,7 @@ mod test {
 
     #[test]
     fn test_peek_first_functions() {
-        let mut rb = RotatingBuffer::new(3);
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
         rb.enqueue(1).unwrap();
         rb.enqueue(2).unwrap();
         assert_eq!(rb.peek(), Some(1))
@@ -368,16 +1391,38 @@ mod test {
 
     #[test]
     fn test_peek_at_functions() {
-        let mut rb = RotatingBuffer::new(3);
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
         rb.enqueue(1).unwrap();
         rb.enqueue(2).unwrap();
         assert_eq!(rb.peek_pos(0), Some(1));
         assert_eq!(rb.peek_pos(1), Some(2));
     }
 
+    #[test]
+    fn test_peek_pos_empty() {
+        let rb = RotatingBuffer::try_new(3).unwrap();
+        assert_eq!(rb.peek_pos(0), None);
+        assert_eq!(rb.peek_pos(1), None);
+    }
+
+    #[test]
+    fn test_try_peek_distinguishes_errors() {
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
+        assert_eq!(rb.try_peek(), Err(RotBufError::Empty));
+        assert_eq!(rb.try_peek_pos(0), Err(RotBufError::Empty));
+        rb.enqueue(1).unwrap();
+        rb.enqueue(2).unwrap();
+        assert_eq!(rb.try_peek_pos(1), Ok(2));
+        assert_eq!(rb.try_peek_last(), Ok(2));
+        assert_eq!(
+            rb.try_peek_pos(2),
+            Err(RotBufError::OutOfBounds { pos: 2, len: 2 })
+        );
+    }
+
     #[test]
     fn test_peek_functions() {
-        let mut rb = RotatingBuffer::new(3);
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
         rb.enqueue(1).unwrap();
         rb.enqueue(2).unwrap();
         assert_eq!(rb.peek(), Some(1));
@@ -385,9 +1430,25 @@ mod test {
         assert_eq!(rb.peek_last(), Some(2));
     }
 
+    #[test]
+    fn test_last_n() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        assert!(rb.last_n(2).is_empty());
+        for value in 1..=4 {
+            rb.enqueue(value).unwrap();
+        }
+        rb.dequeue();
+        rb.dequeue();
+        rb.enqueue(5).unwrap();
+        rb.enqueue(6).unwrap(); // Wraps around
+        assert_eq!(&rb.last_n(3)[..], &[4, 5, 6]);
+        assert_eq!(&rb.last_n(1)[..], &[6]);
+        assert_eq!(&rb.last_n(10)[..], &[3, 4, 5, 6]);
+    }
+
     #[test]
     fn test_len() {
-        let mut rb = RotatingBuffer::new(3);
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
         assert_eq!(rb.len(), 0);
         rb.enqueue(0).unwrap();
         assert_eq!(rb.len(), 1);
@@ -401,9 +1462,21 @@ mod test {
         assert_eq!(rb.len(), 0);
     }
 
+    #[test]
+    fn test_remaining_capacity() {
+        let mut rb = RotatingBuffer::try_new(2).unwrap();
+        assert_eq!(rb.remaining_capacity(), 2);
+        rb.enqueue(0).unwrap();
+        assert_eq!(rb.remaining_capacity(), 1);
+        rb.enqueue(0).unwrap();
+        assert_eq!(rb.remaining_capacity(), 0);
+        rb.dequeue();
+        assert_eq!(rb.remaining_capacity(), 1);
+    }
+
     #[test]
     fn test_len_wrapped() {
-        let mut rb = RotatingBuffer::new(3);
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
         assert_eq!(rb.len(), 0);
         rb.enqueue(1).unwrap();
         assert_eq!(rb.len(), 1);
@@ -426,7 +1499,7 @@ mod test {
 
     #[test]
     fn test_wrapping() {
-        let mut rb = RotatingBuffer::new(3);
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
         rb.enqueue(1).unwrap();
         rb.enqueue(2).unwrap();
         rb.dequeue().unwrap(); // Remove 1
@@ -436,4 +1509,477 @@ mod test {
         assert_eq!(rb.dequeue(), Some(3));
         assert_eq!(rb.dequeue(), Some(4));
     }
+
+    #[test]
+    fn test_overwrite_pos() {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        rb.enqueue_partial(&[0, 0, 0, 0]);
+        rb.consume_head(3);
+        // Reserve a two byte length prefix, then write the payload, wrapping around.
+        rb.enqueue_partial(&[0, 0, 7, 7, 7]);
+        rb.overwrite_pos(1, &[0, 3]).unwrap();
+        assert_eq!(rb.last_n(6).to_vec(), vec![0, 0, 3, 7, 7, 7]);
+
+        rb.overwrite_pos(4, &[8, 9]).unwrap();
+        assert_eq!(rb.last_n(6).to_vec(), vec![0, 0, 3, 7, 8, 9]);
+        assert_eq!(
+            rb.overwrite_pos(5, &[1, 1]),
+            Err(RotBufError::OutOfBounds { pos: 6, len: 6 })
+        );
+        assert_eq!(
+            rb.overwrite_pos(usize::MAX, &[1]),
+            Err(RotBufError::OutOfBounds { pos: usize::MAX, len: 6 })
+        );
+        assert_eq!(rb.peek_last(), Some(9));
+    }
+
+    #[test]
+    fn test_enqueue_vectored() {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        rb.enqueue_partial(&[0, 0, 0]);
+        rb.consume_head(3);
+        assert_eq!(rb.enqueue_vectored(&[&[1], &[2, 2, 2], &[], &[3]]), Ok(5));
+        assert_eq!(
+            rb.enqueue_vectored(&[&[4], &[4]]),
+            Err(RotBufError::Incomplete {
+                written: 0,
+                remaining: 2
+            })
+        );
+        assert_eq!(rb.len(), 5);
+        assert_eq!(rb.last_n(5).to_vec(), vec![1, 2, 2, 2, 3]);
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[0, 0, 1]);
+        rb.consume_head(2);
+        rb.enqueue_partial(&[2, 3]); // Wraps around
+        rb.resize(6).unwrap();
+        assert_eq!(rb.capacity(), 6);
+        rb.enqueue_partial(&[4, 5, 6]);
+        assert!(rb.at_capacity());
+        assert_eq!(rb.resize(5), Err(RotBufError::InvalidCapacity(5)));
+
+        rb.resize(6).unwrap();
+        assert!(rb.at_capacity());
+        assert_eq!(rb.last_n(6).to_vec(), vec![1, 2, 3, 4, 5, 6]);
+        rb.consume_head(5);
+        rb.resize(1).unwrap();
+        assert_eq!(rb.dequeue(), Some(6));
+        assert_eq!(rb.resize(0), Err(RotBufError::InvalidCapacity(0)));
+    }
+
+    #[test]
+    fn test_enqueue_slice() {
+        let mut rb = RotatingBuffer::try_new(5).unwrap();
+        rb.enqueue_partial(&[0, 0]);
+        rb.consume_head(2);
+        assert_eq!(rb.enqueue_slice(&[1, 2, 3, 4]), Ok(4)); // Wraps around
+        let err = rb.enqueue_slice(&[5, 6, 7]).unwrap_err();
+        assert_eq!(err.written(), 1);
+        assert_eq!(err.remainder(), &[6, 7]);
+        assert_eq!(rb.enqueue_slice(&[]), Ok(0));
+        assert_eq!(rb.last_n(5).to_vec(), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_stream_offsets() {
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
+        for round in 0..4u64 {
+            rb.enqueue(0).unwrap();
+            rb.enqueue_partial(&[1, 2]);
+            assert_eq!(rb.enqueue_offset(), 3 * round + 3);
+            rb.dequeue();
+            rb.consume_head(2);
+            assert_eq!(rb.dequeue_offset(), rb.enqueue_offset());
+        }
+        rb.enqueue_partial(&[7, 8]);
+        rb.dequeue();
+        assert_eq!(rb.dequeue_offset() + rb.len() as u64, rb.enqueue_offset());
+        assert_eq!(rb.dequeue_offset(), 13);
+    }
+
+    #[test]
+    fn test_dequeue_bytes() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[0, 0, 1]);
+        rb.consume_head(2);
+        rb.enqueue_partial(&[2, 3, 4]); // Wraps around
+        assert_eq!(rb.dequeue_bytes(5), None);
+        assert_eq!(rb.dequeue_bytes(3), Some(Bytes::from_static(&[1, 2, 3])));
+        assert_eq!(rb.dequeue_bytes(0), Some(Bytes::new()));
+        assert_eq!(rb.dequeue_bytes(1), Some(Bytes::from_static(&[4])));
+        assert!(rb.is_empty());
+    }
+
+    #[test]
+    fn test_zero_on_dequeue() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.set_zero_on_dequeue(true);
+        rb.enqueue_partial(&[1, 2, 3]);
+        assert_eq!(rb.dequeue(), Some(1));
+        rb.consume_head(1);
+        rb.enqueue_partial(&[4, 5, 6]); // Wraps around
+        assert_eq!(&rb.buffer[..], &[5, 6, 3, 4]);
+        rb.remove_range(2, 3);
+        assert_eq!(&rb.buffer[..], &[6, 0, 3, 4]);
+        rb.remove_range(0, 2);
+        assert_eq!(&rb.buffer[..], &[6, 0, 0, 0]);
+        assert_eq!(rb.dequeue(), Some(6));
+        assert!(rb.buffer.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
+        rb.enqueue_partial(&[1, 2, 3]);
+        rb.clear();
+        assert!(rb.is_empty());
+        assert_eq!(rb.dequeue_offset(), 3);
+        assert_eq!((rb.head, rb.tail), (Index::ZERO, Index::ZERO));
+        rb.enqueue_partial(&[4, 5, 6]);
+        assert_eq!(rb.dequeue(), Some(4));
+
+        rb.clear_and_zero();
+        assert!(rb.is_empty());
+        assert!(rb.buffer.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_poisoned_refuses_operations() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[1, 2]);
+        rb.poisoned = true;
+        assert!(rb.is_poisoned());
+        assert!(rb.enqueue(3).is_err());
+        assert_eq!(rb.enqueue_partial(&[3]), 0);
+        assert_eq!(rb.dequeue(), None);
+        assert_eq!(rb.try_peek(), Err(RotBufError::Poisoned));
+        assert_eq!(rb.resize(8), Err(RotBufError::Poisoned));
+        assert!(!rb.debug_validate().is_valid());
+        assert_eq!(rb.len(), 2);
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))]
+    fn test_broken_invariant_poisons_in_release() {
+        let mut rb = RotatingBuffer::try_new(2).unwrap();
+        rb.consume_head(1);
+        assert!(rb.is_poisoned());
+        assert!(rb.is_empty());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Cannot consume more bytes than are queued")]
+    fn test_broken_invariant_panics_in_debug() {
+        RotatingBuffer::try_new(2).unwrap().consume_head(1);
+    }
+
+    #[test]
+    fn test_advance_and_skip() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[1, 2, 3, 4]);
+        assert_eq!(rb.advance(9), Err(RotBufError::OutOfBounds { pos: 4, len: 4 }));
+        assert_eq!(rb.advance(2), Ok(()));
+        assert_eq!(rb.peek(), Some(3));
+        assert_eq!(rb.skip(5), 2);
+        assert_eq!(rb.skip(1), 0);
+        assert_eq!(rb.advance(0), Ok(()));
+        assert!(rb.is_empty());
+    }
+
+    #[test]
+    fn test_truncate_front_and_back() {
+        let mut rb = RotatingBuffer::try_new(5).unwrap();
+        rb.enqueue_partial(&[0, 0, 0]);
+        rb.consume_head(3);
+        rb.enqueue_partial(&[1, 2, 3, 4, 5]); // Wraps around
+        assert_eq!(rb.truncate_back(3), 3);
+        assert_eq!(rb.enqueue_offset() - rb.dequeue_offset(), 2);
+        rb.enqueue_partial(&[6, 7]);
+        assert_eq!(rb.truncate_front(1), 1);
+        assert_eq!(rb.last_n(3).to_vec(), vec![2, 6, 7]);
+        assert_eq!(rb.truncate_back(9), 3);
+        assert_eq!(rb.truncate_front(1), 0);
+        assert!(rb.is_empty());
+    }
+
+    #[test]
+    fn test_fill() {
+        let mut rb = RotatingBuffer::try_new(5).unwrap();
+        rb.enqueue_partial(&[1, 2, 3]);
+        rb.consume_head(2);
+        assert_eq!(rb.fill(0, 4), Ok(())); // Wraps around
+        assert_eq!(
+            rb.fill(0, 1),
+            Err(RotBufError::Incomplete {
+                written: 0,
+                remaining: 1
+            })
+        );
+        assert_eq!(rb.last_n(5).to_vec(), vec![3, 0, 0, 0, 0]);
+        rb.consume_head(1);
+        assert_eq!(rb.fill(7, 0), Ok(()));
+        assert_eq!(rb.len(), 4);
+    }
+
+    #[test]
+    fn test_peek_up_to() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        assert_eq!(rb.peek_up_to(5), (&[][..], &[][..]));
+        rb.enqueue_partial(&[0, 0, 1]);
+        rb.consume_head(2);
+        rb.enqueue_partial(&[2, 3]); // Wraps around
+        assert_eq!(rb.peek_up_to(2), (&[1, 2][..], &[][..]));
+        assert_eq!(rb.peek_up_to(5), (&[1, 2][..], &[3][..]));
+        assert_eq!(rb.len(), 3);
+    }
+
+    #[test]
+    fn test_swap_remove_back() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[0, 0, 1]);
+        rb.consume_head(2);
+        rb.enqueue_partial(&[2, 3, 4]); // Wraps around
+        assert_eq!(rb.swap_remove_back(4), None);
+        assert_eq!(rb.swap_remove_back(0), Some(1));
+        assert_eq!(rb.last_n(3).to_vec(), vec![4, 2, 3]);
+        assert_eq!(rb.swap_remove_back(2), Some(3));
+        assert_eq!(rb.last_n(2).to_vec(), vec![4, 2]);
+        assert_eq!(rb.enqueue_offset() - rb.dequeue_offset(), 2);
+    }
+
+    #[test]
+    fn test_as_slices() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[1, 2]);
+        assert_eq!(rb.as_slices(), (&[1, 2][..], &[][..]));
+        rb.consume_head(1);
+        rb.enqueue_partial(&[3, 4, 5]); // Wraps around
+        assert_eq!(rb.as_slices(), (&[2, 3, 4][..], &[5][..]));
+    }
+
+    #[test]
+    fn test_make_contiguous() {
+        // Each branch: room for the head segment, room for the wrapped bytes, and neither.
+        for (capacity, skipped, queued) in [(8, 6, 4), (8, 2, 7), (5, 2, 5)] {
+            let mut rb = RotatingBuffer::try_new(capacity).unwrap();
+            rb.enqueue_partial(&vec![0; skipped]);
+            rb.consume_head(skipped);
+            let expected: Vec<u8> = (1..=queued).collect();
+            rb.enqueue_partial(&expected); // Wraps around
+            assert!(!rb.as_slices().1.is_empty());
+            assert_eq!(rb.make_contiguous().to_vec(), expected);
+            assert_eq!(rb.as_slices(), (&expected[..], &[][..]));
+            assert!(rb.debug_validate().is_valid());
+            if !rb.at_capacity() {
+                rb.enqueue(0).unwrap();
+                assert_eq!(rb.peek_last(), Some(0));
+            }
+            assert_eq!(rb.dequeue(), Some(1));
+        }
+        assert!(RotatingBuffer::try_new(2).unwrap().make_contiguous().is_empty());
+    }
+
+    #[test]
+    fn test_make_contiguous_zeroes_free_slots() {
+        for (capacity, skipped, queued) in [(8, 6, 4), (8, 2, 7), (6, 3, 5)] {
+            let mut rb = RotatingBuffer::try_new(capacity).unwrap();
+            rb.set_zero_on_dequeue(true);
+            rb.enqueue_partial(&vec![0xaa; skipped]);
+            rb.consume_head(skipped);
+            let expected: Vec<u8> = (1..=queued).collect();
+            rb.enqueue_partial(&expected); // Wraps around
+            assert_eq!(rb.make_contiguous().to_vec(), expected);
+
+            let start = rb.head().get();
+            let mut free = rb.buffer[..start].iter().chain(&rb.buffer[start + rb.len()..]);
+            assert!(free.all(|&byte| byte == 0));
+        }
+    }
+
+    #[test]
+    fn test_peek_slice() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[0, 0, 1]);
+        rb.consume_head(2);
+        rb.enqueue_partial(&[2, 3, 4]); // Wraps around
+        let mut header = [0; 3];
+        assert_eq!(rb.peek_slice(1..4, &mut header), Ok(()));
+        assert_eq!(header, [2, 3, 4]);
+        assert_eq!(
+            rb.peek_slice(2..5, &mut header),
+            Err(RotBufError::OutOfBounds { pos: 4, len: 4 })
+        );
+        assert_eq!(header, [2, 3, 4]);
+        assert_eq!(rb.peek_slice(4..4, &mut []), Ok(()));
+        assert_eq!(rb.len(), 4);
+    }
+
+    #[test]
+    fn test_wrap_topology() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        assert!(!rb.is_wrapped());
+        rb.enqueue_partial(&[1, 2, 3, 4]);
+        assert!(!rb.is_wrapped());
+        assert_eq!((rb.first_segment_len(), rb.second_segment_len()), (4, 0));
+        rb.consume_head(3);
+        rb.enqueue_partial(&[5, 6]); // Wraps around
+        assert!(rb.is_wrapped());
+        assert_eq!((rb.first_segment_len(), rb.second_segment_len()), (1, 2));
+        rb.make_contiguous();
+        assert!(!rb.is_wrapped());
+        assert_eq!(rb.first_segment_len(), 3);
+    }
+
+    #[test]
+    fn test_enqueue_buf() {
+        let mut rb = RotatingBuffer::try_new(5).unwrap();
+        let mut src = Bytes::from_static(&[1, 2]).chain(Bytes::from_static(&[3, 4, 5, 6]));
+        assert_eq!(rb.enqueue_buf(&mut src), 5);
+        assert_eq!(src.remaining(), 1);
+        assert_eq!(rb.enqueue_buf(&mut src), 0);
+        rb.consume_head(3);
+        assert_eq!(rb.enqueue_buf(&mut src), 1);
+        assert!(!src.has_remaining());
+        assert_eq!(rb.last_n(3).to_vec(), vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn test_positional_access() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[0, 0, 1]);
+        rb.consume_head(2);
+        rb.enqueue_partial(&[0, 0, 3]); // Wraps around
+        *rb.get_mut(1).unwrap() = 2;
+        rb[2] = 9;
+        rb[2] -= 6;
+        assert_eq!((rb[0], rb.get(1), rb.get(3)), (1, Some(&2), Some(&3)));
+        assert_eq!(rb.get(4), None);
+        assert_eq!(rb.get_mut(4), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_index_out_of_bounds_panics() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[1]);
+        let _ = rb[1];
+    }
+
+    #[test]
+    fn test_dequeue_to_bufmut() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[0, 0, 1]);
+        rb.consume_head(2);
+        rb.enqueue_partial(&[2, 3, 4]); // Wraps around
+        let mut array = [0; 3];
+        assert_eq!(rb.dequeue_to_bufmut(&mut &mut array[..]), 3);
+        assert_eq!(array, [1, 2, 3]);
+        let mut out = Vec::new();
+        assert_eq!(rb.dequeue_to_bufmut(&mut out), 1);
+        assert_eq!(rb.dequeue_to_bufmut(&mut out), 0);
+        assert_eq!(out, vec![4]);
+    }
+
+    #[test]
+    fn test_dequeue_into_vectored() {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        rb.enqueue_partial(&[0; 4]);
+        rb.consume_head(4);
+        rb.enqueue_partial(&[1, 2, 3, 4, 5]); // Wraps around
+        let (mut header, mut body) = ([0; 2], [0; 4]);
+        assert_eq!(rb.dequeue_into_vectored(&mut [&mut header, &mut body]), 5);
+        assert_eq!((header, body), ([1, 2], [3, 4, 5, 0]));
+        assert_eq!(rb.dequeue_into_vectored(&mut [&mut header]), 0);
+        assert_eq!(rb.dequeue_into_vectored(&mut []), 0);
+    }
+
+    #[test]
+    fn test_contiguous_free_len() {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        assert_eq!(rb.contiguous_free_len(), 6);
+        rb.enqueue_partial(&[1, 2, 3, 4]);
+        rb.consume_head(3);
+        assert_eq!(rb.contiguous_free_len(), 2);
+        rb.enqueue_partial(&[5, 6, 7]); // Wraps around
+        assert_eq!(rb.contiguous_free_len(), 2);
+        rb.enqueue_partial(&[8, 9]);
+        assert_eq!(rb.contiguous_free_len(), 0);
+        let budget = MemoryBudget::new(0);
+        rb.consume_head(6);
+        rb.attach_budget(&budget);
+        assert_eq!(rb.contiguous_free_len(), 0);
+    }
+
+    #[test]
+    fn test_fork_into() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[0, 0, 1]);
+        rb.consume_head(2);
+        rb.enqueue_partial(&[2, 3, 4]); // Wraps around
+        let mut mirror = RotatingBuffer::try_new(6).unwrap();
+        mirror.enqueue_partial(&[9]);
+        assert_eq!(rb.fork_into(&mut mirror), Ok(4));
+        assert_eq!(mirror.last_n(6).to_vec(), vec![9, 1, 2, 3, 4]);
+        assert_eq!(
+            rb.fork_into(&mut mirror),
+            Err(RotBufError::Incomplete {
+                written: 0,
+                remaining: 4
+            })
+        );
+        assert_eq!(rb.len(), 4);
+    }
+
+    #[test]
+    fn test_fmt_write() {
+        use std::fmt::Write;
+
+        let mut rb = RotatingBuffer::try_new(8).unwrap();
+        write!(rb, "{}-{:x}", 12, 171).unwrap();
+        assert_eq!(rb.last_n(8).to_vec(), b"12-ab".to_vec());
+        assert!(write!(rb, "\u{e9}\u{e9}").is_err()); // 4 bytes
+        assert!(rb.write_char('\u{e9}').is_ok());
+        assert!(rb.write_str("!").is_ok());
+        assert!(rb.write_char('x').is_err());
+        assert_eq!(rb.len(), 8);
+    }
+
+    #[test]
+    fn test_freeze_front() {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        rb.enqueue_partial(&[0, 1, 2, 3, 4]);
+        rb.consume_head(1);
+        let frozen = rb.freeze_front(3).unwrap();
+        assert_eq!(frozen, Bytes::from_static(&[1, 2, 3]));
+        assert_eq!(rb.dequeue_offset(), 4);
+        assert_eq!(rb.remaining_capacity(), 5);
+        rb.enqueue_partial(&[5, 6, 7, 8, 9, 10]);
+        assert_eq!(rb.last_n(6).to_vec(), vec![4, 5, 6, 7, 8, 9]);
+        assert!(rb.debug_validate().is_valid());
+
+        // Bytes that wrap around are copied.
+        rb.consume_head(3);
+        rb.enqueue_partial(&[10, 11]); // Wraps around
+        assert_eq!(rb.freeze_front(4).unwrap(), Bytes::from_static(&[7, 8, 9, 10]));
+        assert_eq!(rb.freeze_front(2), None);
+        assert_eq!(frozen, Bytes::from_static(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn test_with_slices() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[0, 0, 1]);
+        rb.consume_head(2);
+        rb.enqueue_partial(&[2, 3]); // Wraps around
+        let sum = rb.with_slices(|first, second| {
+            assert_eq!((first, second), (&[1, 2][..], &[3][..]));
+            first.iter().chain(second).map(|&value| value as u32).sum::<u32>()
+        });
+        assert_eq!(sum, 6);
+    }
 }
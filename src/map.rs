@@ -0,0 +1,203 @@
+//! Many [RotatingBuffer]s keyed by e.g. connection id, sharing a memory budget.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::{RotBufError, RotatingBuffer};
+
+/// The [RotatingBufferMap] manages a fixed-capacity [RotatingBuffer] per key (such as a
+/// connection id), keeping the total capacity of all of its rings within a memory budget.
+///
+/// When a new ring would exceed the budget, the least recently used ring that is idle
+/// (empty) is evicted to make room.  Rings holding data are never evicted implicitly.
+#[derive(Debug)]
+pub struct RotatingBufferMap<K> {
+    rings: HashMap<K, Entry>,
+    ring_capacity: usize,
+    memory_budget: usize,
+    /// Incremented on every access, to order rings by how recently they were used.
+    clock: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    ring: RotatingBuffer,
+    last_used: u64,
+}
+
+impl<K: Hash + Eq + Clone> RotatingBufferMap<K> {
+    /// Creates a new [RotatingBufferMap] whose rings each hold `ring_capacity` bytes, with
+    /// at most `memory_budget` bytes of capacity allocated across all rings.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if `ring_capacity` is 0.  [RotatingBufferMap::try_new] reports that as an
+    /// error instead.
+    pub fn new(ring_capacity: usize, memory_budget: usize) -> Self {
+        match Self::try_new(ring_capacity, memory_budget) {
+            Ok(map) => map,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Creates a new [RotatingBufferMap] as in [RotatingBufferMap::new], returning an
+    /// [Err] with [RotBufError::InvalidCapacity] if `ring_capacity` is 0.
+    pub fn try_new(ring_capacity: usize, memory_budget: usize) -> Result<Self, RotBufError> {
+        if ring_capacity == 0 {
+            return Err(RotBufError::InvalidCapacity(ring_capacity));
+        }
+        Ok(Self {
+            rings: HashMap::new(),
+            ring_capacity,
+            memory_budget,
+            clock: 0,
+        })
+    }
+
+    /// Returns the capacity of every ring.
+    pub fn ring_capacity(&self) -> usize {
+        self.ring_capacity
+    }
+
+    /// Returns the memory budget.
+    pub fn memory_budget(&self) -> usize {
+        self.memory_budget
+    }
+
+    /// Returns the total capacity of every ring currently allocated.
+    pub fn memory_used(&self) -> usize {
+        self.rings.len() * self.ring_capacity
+    }
+
+    /// Returns the number of rings.
+    pub fn len(&self) -> usize {
+        self.rings.len()
+    }
+
+    /// Returns whether or not there are no rings.
+    pub fn is_empty(&self) -> bool {
+        self.rings.is_empty()
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// Returns the ring for `key`, without marking it as used.
+    pub fn get(&self, key: &K) -> Option<&RotatingBuffer> {
+        self.rings.get(key).map(|entry| &entry.ring)
+    }
+
+    /// Returns the ring for `key`, marking it as the most recently used.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut RotatingBuffer> {
+        let now = self.tick();
+        self.rings.get_mut(key).map(|entry| {
+            entry.last_used = now;
+            &mut entry.ring
+        })
+    }
+
+    /// Returns the ring for `key`, creating it if needed, and marks it as the most recently
+    /// used.
+    ///
+    /// Returns [None] if creating the ring would exceed the memory budget and there is no
+    /// idle ring left to evict.
+    pub fn get_or_create(&mut self, key: K) -> Option<&mut RotatingBuffer> {
+        if !self.rings.contains_key(&key) {
+            while self.memory_used() + self.ring_capacity > self.memory_budget {
+                self.evict_lru_idle()?;
+            }
+            let ring = RotatingBuffer::with_size(self.ring_capacity);
+            self.rings.insert(key.clone(), Entry { ring, last_used: 0 });
+        }
+        self.get_mut(&key)
+    }
+
+    /// Removes and returns the ring for `key`.
+    pub fn remove(&mut self, key: &K) -> Option<RotatingBuffer> {
+        self.rings.remove(key).map(|entry| entry.ring)
+    }
+
+    /// Removes the least recently used idle (empty) ring, returning its key, or [None] if
+    /// every ring holds data.
+    pub fn evict_lru_idle(&mut self) -> Option<K> {
+        let key = self
+            .rings
+            .iter()
+            .filter(|(_, entry)| entry.ring.is_empty())
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone())?;
+        self.rings.remove(&key);
+        Some(key)
+    }
+
+    /// Returns an iterator over the rings holding data, in arbitrary order.
+    pub fn iter_non_empty(&self) -> impl Iterator<Item = (&K, &RotatingBuffer)> {
+        self.rings
+            .iter()
+            .filter(|(_, entry)| !entry.ring.is_empty())
+            .map(|(key, entry)| (key, &entry.ring))
+    }
+
+    /// Returns an iterator over mutable references to the rings holding data, in arbitrary
+    /// order.  Iterating does not mark the rings as used.
+    pub fn iter_non_empty_mut(&mut self) -> impl Iterator<Item = (&K, &mut RotatingBuffer)> {
+        self.rings
+            .iter_mut()
+            .filter(|(_, entry)| !entry.ring.is_empty())
+            .map(|(key, entry)| (key, &mut entry.ring))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used_idle_ring() {
+        let mut map = RotatingBufferMap::new(4, 12);
+        map.get_or_create(1).unwrap();
+        map.get_or_create(2).unwrap().enqueue(2).unwrap();
+        map.get_or_create(3).unwrap();
+        assert_eq!(map.memory_used(), 12);
+
+        // Ring 1 is the least recently used, but touching it leaves ring 3 to be evicted.
+        map.get_mut(&1);
+        map.get_or_create(4).unwrap();
+        assert!(map.get(&3).is_none());
+        assert_eq!(map.memory_used(), 12);
+
+        // Ring 2 holds data, so it survives even once it is the least recently used.
+        map.get_or_create(5).unwrap();
+        assert!(map.get(&1).is_none());
+        assert_eq!(map.get(&2).unwrap().peek(), Some(2));
+    }
+
+    #[test]
+    fn test_try_new_rejects_zero_ring_capacity() {
+        assert_eq!(
+            RotatingBufferMap::<u32>::try_new(0, 8).unwrap_err(),
+            RotBufError::InvalidCapacity(0)
+        );
+    }
+
+    #[test]
+    fn test_budget_exhausted_by_busy_rings() {
+        let mut map = RotatingBufferMap::new(4, 8);
+        map.get_or_create("a").unwrap().enqueue(1).unwrap();
+        map.get_or_create("b").unwrap().enqueue(2).unwrap();
+        assert!(map.get_or_create("c").is_none());
+        assert_eq!(map.evict_lru_idle(), None);
+
+        let mut busy: Vec<_> = map.iter_non_empty().map(|(key, _)| *key).collect();
+        busy.sort();
+        assert_eq!(busy, vec!["a", "b"]);
+
+        map.get_mut(&"a").unwrap().dequeue();
+        assert_eq!(map.iter_non_empty().count(), 1);
+        assert!(map.get_or_create("c").is_some());
+        assert!(map.get(&"a").is_none());
+    }
+}
@@ -0,0 +1,79 @@
+//! What a [RotatingBuffer] gives up when an enqueue does not fit.
+
+use crate::RotatingBuffer;
+
+/// The [OverflowPolicy] of a [RotatingBuffer] decides which data is lost when
+/// [RotatingBuffer::enqueue] or [RotatingBuffer::enqueue_slice] is given more than it has
+/// room for.  Set with [RotatingBuffer::set_overflow_policy].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Keep the queued bytes and reject the incoming ones that do not fit, keeping
+    /// continuity (e.g. for audio output).  This is the default.
+    #[default]
+    DropNewest,
+    /// Dequeue and discard the oldest queued bytes to make room for the incoming ones,
+    /// keeping recency (e.g. for logs).
+    DropOldest,
+}
+
+impl RotatingBuffer {
+    /// Sets which data is lost when an enqueue does not fit.
+    ///
+    /// The policy applies to [RotatingBuffer::enqueue] and [RotatingBuffer::enqueue_slice].
+    /// With [OverflowPolicy::DropOldest] they evict just enough of the oldest bytes to fit
+    /// the new ones; evicted bytes count as dequeued, so
+    /// [RotatingBuffer::dequeue_offset] tells how many were lost.  A slice longer than the
+    /// whole ring still only has its first [RotatingBuffer::capacity] bytes enqueued.
+    ///
+    /// All-or-nothing enqueues, such as [RotatingBuffer::enqueue_frame], and writes into
+    /// the free space, such as through [bytes::BufMut] or [std::io::Write], never evict, as
+    /// dropping part of the queue under them would tear the messages it holds.
+    pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.overflow_policy = policy;
+    }
+
+    /// Returns the [OverflowPolicy] of the [RotatingBuffer].
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow_policy
+    }
+
+    /// Evicts the oldest bytes, if the policy is [OverflowPolicy::DropOldest], until `n`
+    /// bytes fit or nothing is left to evict.
+    pub(crate) fn make_room(&mut self, n: usize) {
+        if self.overflow_policy == OverflowPolicy::DropOldest && !self.poisoned {
+            let short = n.saturating_sub(self.remaining_capacity());
+            self.consume_head(short.min(self.len()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_drop_newest() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        assert_eq!(rb.overflow_policy(), OverflowPolicy::DropNewest);
+        rb.enqueue_partial(&[1, 2, 3]);
+        assert_eq!(rb.enqueue_slice(&[4, 5]).unwrap_err().written(), 1);
+        assert!(rb.enqueue(6).is_err());
+        assert_eq!(rb.last_n(4).to_vec(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.set_overflow_policy(OverflowPolicy::DropOldest);
+        rb.enqueue_partial(&[1, 2, 3]);
+        assert_eq!(rb.enqueue_slice(&[4, 5]), Ok(2));
+        rb.enqueue(6).unwrap();
+        assert_eq!(rb.last_n(4).to_vec(), vec![3, 4, 5, 6]);
+        assert_eq!(rb.dequeue_offset(), 2);
+
+        assert_eq!(rb.enqueue_slice(&[7; 6]).unwrap_err().written(), 4);
+        assert_eq!(rb.last_n(4).to_vec(), vec![7; 4]);
+        assert!(rb.enqueue_frame(&[]).is_err());
+    }
+}
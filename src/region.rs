@@ -0,0 +1,310 @@
+//! Named [RotatingBuffer]s sharing one allocation whose split can be rebalanced at runtime.
+
+use bytes::BytesMut;
+
+use crate::index::Capacity;
+use crate::{RotBufError, RotatingBuffer};
+
+/// The [RegionedBuffer] partitions one allocation of a fixed capacity budget (e.g. the
+/// memory allowed per connection) between named rings, such as `"control"` and `"data"`,
+/// each with its own head and tail.
+///
+/// The split is not fixed: [RegionedBuffer::rebalance] moves capacity from one region to
+/// another, and [RegionedBuffer::set_capacity] grows a region into (or shrinks it back to)
+/// the unallocated part of the budget, all without losing queued bytes.  Regions are laid
+/// out back to back in the allocation, so changing the split moves the queued bytes of the
+/// regions between the ones involved rather than reallocating.
+///
+/// Resizing a region directly, through [RotatingBuffer::resize], moves it into an
+/// allocation of its own until the split is next changed.
+#[derive(Debug)]
+pub struct RegionedBuffer {
+    /// The name and ring of every region, in the order they were given (and laid out).
+    regions: Vec<(&'static str, RotatingBuffer)>,
+    /// The unallocated end of the allocation.
+    spare: BytesMut,
+    budget: usize,
+}
+
+impl RegionedBuffer {
+    /// Creates a new [RegionedBuffer] with the given `(name, capacity)` regions, within a
+    /// total capacity of `budget` bytes.
+    ///
+    /// Returns an [Err] with [RotBufError::InvalidCapacity] if a region has a capacity of
+    /// 0, or the regions' capacities add up to more than `budget` (reporting their total).
+    ///
+    /// # PANICS
+    ///
+    /// Panics if two regions have the same name.
+    pub fn new(budget: usize, regions: &[(&'static str, usize)]) -> Result<Self, RotBufError> {
+        let mut total = 0usize;
+        for (i, &(name, capacity)) in regions.iter().enumerate() {
+            if regions[..i].iter().any(|&(other, _)| other == name) {
+                panic!("Region `{}` is defined more than once", name);
+            }
+            if capacity == 0 {
+                return Err(RotBufError::InvalidCapacity(capacity));
+            }
+            total = total.saturating_add(capacity);
+        }
+        if total > budget {
+            return Err(RotBufError::InvalidCapacity(total));
+        }
+
+        let mut spare = BytesMut::zeroed(budget);
+        let regions = regions
+            .iter()
+            .map(|&(name, capacity)| {
+                let size = Capacity::new(capacity).expect("the capacity was checked");
+                (
+                    name,
+                    RotatingBuffer::with_buffer(spare.split_to(capacity), size),
+                )
+            })
+            .collect();
+        Ok(Self {
+            regions,
+            spare,
+            budget,
+        })
+    }
+
+    /// Returns the total capacity the regions may share.
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Returns the total capacity currently given to the regions.
+    pub fn allocated(&self) -> usize {
+        self.regions.iter().map(|(_, ring)| ring.capacity()).sum()
+    }
+
+    /// Returns the names of the regions, in the order they were given.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.regions.iter().map(|&(name, _)| name)
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.regions.iter().position(|&(other, _)| other == name)
+    }
+
+    fn expect_position(&self, name: &str) -> usize {
+        self.position(name)
+            .unwrap_or_else(|| panic!("There is no region named `{}`", name))
+    }
+
+    /// Returns the ring of the region named `name`, or [None] if there is none.
+    pub fn region(&self, name: &str) -> Option<&RotatingBuffer> {
+        self.position(name).map(|i| &self.regions[i].1)
+    }
+
+    /// Returns the ring of the region named `name` mutably, or [None] if there is none.
+    pub fn region_mut(&mut self, name: &str) -> Option<&mut RotatingBuffer> {
+        self.position(name).map(|i| &mut self.regions[i].1)
+    }
+
+    /// Resizes the region named `name` to `capacity`, taking the growth from (or returning
+    /// the shrinkage to) the unallocated part of the budget.
+    ///
+    /// Returns an [Err] with [RotBufError::InvalidCapacity] if `capacity` would exceed the
+    /// budget, or as with [RotatingBuffer::resize], in which case nothing is changed.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if there is no region named `name`.
+    pub fn set_capacity(&mut self, name: &str, capacity: usize) -> Result<(), RotBufError> {
+        let i = self.expect_position(name);
+        let others = self.allocated() - self.regions[i].1.capacity();
+        if others.saturating_add(capacity) > self.budget {
+            return Err(RotBufError::InvalidCapacity(capacity));
+        }
+        let mut capacities = self.capacities();
+        capacities[i] = capacity;
+        self.repartition(&capacities)
+    }
+
+    /// Moves `n` bytes of capacity from the region named `from` to the region named `to`.
+    ///
+    /// Returns an [Err] with [RotBufError::InvalidCapacity] if `from` would be left with
+    /// no capacity or less than it currently holds, or as with [RotatingBuffer::resize], in
+    /// which case nothing is changed.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if either region does not exist.
+    pub fn rebalance(&mut self, from: &str, to: &str, n: usize) -> Result<(), RotBufError> {
+        let (from, to) = (self.expect_position(from), self.expect_position(to));
+        if from == to || n == 0 {
+            return Ok(());
+        }
+        let mut capacities = self.capacities();
+        capacities[from] = capacities[from].saturating_sub(n);
+        capacities[to] = capacities[to].saturating_add(n);
+        self.repartition(&capacities)
+    }
+
+    fn capacities(&self) -> Vec<usize> {
+        self.regions
+            .iter()
+            .map(|(_, ring)| ring.capacity())
+            .collect()
+    }
+
+    /// Lays the regions out again with the given capacities, which must fit in the budget,
+    /// keeping their queued bytes.
+    fn repartition(&mut self, capacities: &[usize]) -> Result<(), RotBufError> {
+        let mut sizes = Vec::with_capacity(capacities.len());
+        for ((_, ring), &capacity) in self.regions.iter().zip(capacities) {
+            ring.check_poisoned()?;
+            match Capacity::new(capacity) {
+                Some(size) if capacity >= ring.len() => sizes.push(size),
+                _ => return Err(RotBufError::InvalidCapacity(capacity)),
+            }
+        }
+
+        // Join the windows back into the whole allocation, noting where each queue starts
+        // and where it must move to.  Joining is O(1) per window unless a region was resized
+        // into an allocation of its own.
+        let mut arena = BytesMut::new();
+        let mut moves = Vec::with_capacity(self.regions.len());
+        let mut target = 0;
+        for ((_, ring), &capacity) in self.regions.iter_mut().zip(capacities) {
+            ring.make_contiguous();
+            moves.push((arena.len() + ring.head().get(), target, ring.len()));
+            target += capacity;
+            let mut window = std::mem::take(&mut ring.buffer);
+            window.resize(ring.capacity(), 0);
+            arena.unsplit(window);
+        }
+        arena.unsplit(std::mem::take(&mut self.spare));
+        if arena.len() < self.budget {
+            arena.resize(self.budget, 0);
+        }
+
+        // Regions keep their order, so moving the queues that go left in order, then those
+        // that go right in reverse order, never overwrites a queue that has yet to move.
+        for &(from, to, len) in moves.iter().filter(|&&(from, to, _)| to < from) {
+            arena.copy_within(from..from + len, to);
+        }
+        for &(from, to, len) in moves.iter().rev().filter(|&&(from, to, _)| to > from) {
+            arena.copy_within(from..from + len, to);
+        }
+
+        for ((_, ring), size) in self.regions.iter_mut().zip(sizes) {
+            ring.adopt_buffer(arena.split_to(size.get()), size);
+        }
+        self.spare = arena;
+
+        // Moving leaves stale copies of queued bytes in the free space.
+        let secret = self.regions.iter().any(|(_, ring)| ring.zero_on_dequeue);
+        if secret || cfg!(feature = "zeroize") {
+            for (_, ring) in &mut self.regions {
+                let (first, second) = ring.free_slots_mut();
+                first.fill(0);
+                second.fill(0);
+            }
+            self.spare.fill(0);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_regions_share_budget() {
+        let mut rb = RegionedBuffer::new(16, &[("control", 4), ("data", 8)]).unwrap();
+        assert_eq!(rb.names().collect::<Vec<_>>(), vec!["control", "data"]);
+        assert_eq!(rb.allocated(), 12);
+        assert!(rb.region("missing").is_none());
+
+        rb.region_mut("data")
+            .unwrap()
+            .enqueue_partial(&[1, 2, 3, 4, 5, 6]);
+        rb.region_mut("control").unwrap().enqueue(9).unwrap();
+        assert_eq!(
+            rb.rebalance("data", "control", 3),
+            Err(RotBufError::InvalidCapacity(5))
+        );
+        rb.rebalance("data", "control", 2).unwrap();
+        assert_eq!(rb.region("control").unwrap().capacity(), 6);
+        assert_eq!(
+            rb.region("data").unwrap().last_n(6).to_vec(),
+            vec![1, 2, 3, 4, 5, 6]
+        );
+
+        assert_eq!(
+            rb.set_capacity("control", 11),
+            Err(RotBufError::InvalidCapacity(11))
+        );
+        rb.set_capacity("control", 10).unwrap();
+        assert_eq!(rb.allocated(), rb.budget());
+        assert_eq!(rb.region("control").unwrap().peek(), Some(9));
+    }
+
+    /// Asserts the regions are laid out back to back, followed by the spare capacity.
+    fn assert_one_allocation(rb: &RegionedBuffer) {
+        let mut end = rb.regions[0].1.buffer.as_ptr();
+        for (_, ring) in &rb.regions {
+            assert_eq!(ring.buffer.as_ptr(), end);
+            end = end.wrapping_add(ring.capacity());
+        }
+        assert_eq!(rb.spare.as_ptr(), end);
+    }
+
+    #[test]
+    fn test_regions_share_one_allocation() {
+        let mut rb = RegionedBuffer::new(16, &[("a", 4), ("b", 4), ("c", 4)]).unwrap();
+        assert_one_allocation(&rb);
+        let b = rb.region_mut("b").unwrap();
+        b.enqueue_partial(&[0, 0, 0]);
+        b.consume_head(3);
+        b.enqueue_partial(&[1, 2, 3]); // Wraps around
+        rb.region_mut("c").unwrap().enqueue_partial(&[4, 5]);
+
+        // Grow the first region, pushing the others right, then shrink it back.
+        rb.set_capacity("a", 8).unwrap();
+        assert_one_allocation(&rb);
+        rb.rebalance("a", "c", 6).unwrap();
+        assert_one_allocation(&rb);
+        assert_eq!(
+            rb.capacities(),
+            vec![2, 4, 10],
+            "the spare capacity is unchanged"
+        );
+        assert_eq!(
+            rb.region("b").unwrap().as_slices(),
+            (&[1, 2, 3][..], &[][..])
+        );
+        assert_eq!(rb.region("c").unwrap().as_slices(), (&[4, 5][..], &[][..]));
+
+        // A region resized on its own rejoins the allocation on the next rebalance.
+        rb.region_mut("b").unwrap().resize(3).unwrap();
+        rb.rebalance("c", "b", 1).unwrap();
+        assert_one_allocation(&rb);
+        assert_eq!(rb.region("b").unwrap().last_n(3).to_vec(), vec![1, 2, 3]);
+        assert_eq!(rb.allocated(), 15);
+    }
+
+    #[test]
+    fn test_invalid_regions() {
+        assert_eq!(
+            RegionedBuffer::new(4, &[("a", 3), ("b", 2)]).unwrap_err(),
+            RotBufError::InvalidCapacity(5)
+        );
+        assert_eq!(
+            RegionedBuffer::new(4, &[("a", 0)]).unwrap_err(),
+            RotBufError::InvalidCapacity(0)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "no region named")]
+    fn test_unknown_region_panics() {
+        let mut rb = RegionedBuffer::new(4, &[("a", 2)]).unwrap();
+        rb.rebalance("a", "b", 1).ok();
+    }
+}
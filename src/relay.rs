@@ -0,0 +1,312 @@
+//! A helper that moves data between a socket and a pair of [RotatingBuffer]s.
+
+use std::io::{self, ErrorKind, Read, Write};
+
+use crate::{RotBufError, RotatingBuffer};
+
+/// The outcome of a single [Relay::pump_once].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PumpStats {
+    /// Bytes read from the socket into the inbound ring.
+    pub read: usize,
+    /// Bytes written from the outbound ring to the socket.
+    pub written: usize,
+    /// Whether the socket reported end-of-stream.
+    pub eof: bool,
+}
+
+/// The [Relay] owns a socket along with an inbound and an outbound [RotatingBuffer].
+///
+/// Each [Relay::pump_once] reads from the socket into the free space of the inbound ring
+/// and writes queued outbound bytes to the socket.  Backpressure falls out of the rings'
+/// fixed capacities: nothing is read while the inbound ring has no room (it is full, or
+/// the [crate::MemoryBudget] attached to it is spent), and nothing is written while the
+/// outbound ring is empty.  `WouldBlock` and `Interrupted` are treated as "no progress",
+/// so non-blocking sockets are supported.
+#[derive(Debug)]
+pub struct Relay<S> {
+    socket: S,
+    inbound: RotatingBuffer,
+    outbound: RotatingBuffer,
+    eof: bool,
+}
+
+impl<S: Read + Write> Relay<S> {
+    /// Creates a new [Relay] over `socket` with rings of the given capacities.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if either capacity is 0.  [Relay::try_new] reports that as an error instead.
+    pub fn new(socket: S, inbound_capacity: usize, outbound_capacity: usize) -> Self {
+        match Self::try_new(socket, inbound_capacity, outbound_capacity) {
+            Ok(relay) => relay,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Creates a new [Relay] as in [Relay::new], returning an [Err] with
+    /// [RotBufError::InvalidCapacity] if either capacity is 0.
+    pub fn try_new(
+        socket: S,
+        inbound_capacity: usize,
+        outbound_capacity: usize,
+    ) -> Result<Self, RotBufError> {
+        Ok(Self {
+            socket,
+            inbound: RotatingBuffer::try_new(inbound_capacity)?,
+            outbound: RotatingBuffer::try_new(outbound_capacity)?,
+            eof: false,
+        })
+    }
+
+    /// Returns the ring holding bytes read from the socket.
+    pub fn inbound(&mut self) -> &mut RotatingBuffer {
+        &mut self.inbound
+    }
+
+    /// Returns the ring holding bytes waiting to be written to the socket.
+    pub fn outbound(&mut self) -> &mut RotatingBuffer {
+        &mut self.outbound
+    }
+
+    /// Returns a reference to the underlying socket.
+    pub fn socket(&self) -> &S {
+        &self.socket
+    }
+
+    /// Returns whether the socket has reported end-of-stream.
+    pub fn is_eof(&self) -> bool {
+        self.eof
+    }
+
+    /// Consumes the [Relay], returning the socket and the inbound and outbound rings.
+    pub fn into_parts(self) -> (S, RotatingBuffer, RotatingBuffer) {
+        (self.socket, self.inbound, self.outbound)
+    }
+
+    /// Performs at most one write and one read on the socket.
+    ///
+    /// Queued outbound bytes are written before reading, so that with a blocking socket a
+    /// reply is never held back while waiting on the peer, which may itself be waiting
+    /// for that reply.
+    pub fn pump_once(&mut self) -> io::Result<PumpStats> {
+        let mut stats = PumpStats {
+            eof: self.eof,
+            ..PumpStats::default()
+        };
+
+        if !self.outbound.is_empty() {
+            let (queued, _) = self.outbound.segments();
+            if let Some(n) = no_progress_on_block(self.socket.write(queued))? {
+                if n == 0 {
+                    return Err(ErrorKind::WriteZero.into());
+                }
+                self.outbound.consume_head(n);
+                stats.written = n;
+            }
+        }
+
+        if !self.eof && self.inbound.remaining_capacity() > 0 {
+            let (free, _) = self.inbound.free_segments_mut();
+            match no_progress_on_block(self.socket.read(free))? {
+                Some(0) => {
+                    self.eof = true;
+                    stats.eof = true;
+                }
+                Some(n) => {
+                    self.inbound.commit_tail(n);
+                    stats.read = n;
+                }
+                None => (),
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Pumps the socket until it reaches end-of-stream and the outbound ring has been
+    /// flushed.  After every pump, `on_pump` is given the inbound and outbound rings to
+    /// consume received data and queue replies.
+    ///
+    /// When a round moves no bytes (e.g. a non-blocking socket reports `WouldBlock`), the
+    /// thread yields before pumping again.  If the inbound ring is full, nothing is queued
+    /// to write and `on_pump` consumes nothing, no further round could make progress, so
+    /// this fails with an [ErrorKind::Other] error instead of looping forever.
+    pub fn run<F>(&mut self, mut on_pump: F) -> io::Result<()>
+    where
+        F: FnMut(&mut RotatingBuffer, &mut RotatingBuffer) -> io::Result<()>,
+    {
+        loop {
+            let was_eof = self.eof;
+            let stats = self.pump_once()?;
+            let consumed = self.inbound.dequeue_offset();
+            let queued = self.outbound.enqueue_offset();
+            on_pump(&mut self.inbound, &mut self.outbound)?;
+            if stats.eof && self.outbound.is_empty() {
+                return self.socket.flush();
+            }
+
+            let progressed = stats.read > 0
+                || stats.written > 0
+                || stats.eof != was_eof
+                || self.inbound.dequeue_offset() != consumed
+                || self.outbound.enqueue_offset() != queued;
+            if !progressed {
+                if self.inbound.at_capacity() && self.outbound.is_empty() {
+                    return Err(io::Error::other(
+                        "inbound ring is full and on_pump consumed nothing from it",
+                    ));
+                }
+                std::thread::yield_now();
+            }
+        }
+    }
+}
+
+/// Maps errors that only mean "try again later" to `Ok(None)`.
+fn no_progress_on_block(result: io::Result<usize>) -> io::Result<Option<usize>> {
+    match result {
+        Ok(n) => Ok(Some(n)),
+        Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted) => {
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::io::Cursor;
+
+    /// A fake socket reading from a fixed input and recording everything written.
+    struct FakeSocket {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for FakeSocket {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for FakeSocket {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_try_new_rejects_zero_capacity() {
+        let err = Relay::try_new(Cursor::new(Vec::new()), 4, 0).unwrap_err();
+        assert_eq!(err, RotBufError::InvalidCapacity(0));
+    }
+
+    #[test]
+    fn test_pump_respects_inbound_capacity() {
+        let socket = FakeSocket {
+            input: Cursor::new(vec![1, 2, 3, 4, 5]),
+            output: Vec::new(),
+        };
+        let mut relay = Relay::new(socket, 3, 3);
+        let stats = relay.pump_once().unwrap();
+        assert_eq!(stats.read, 3);
+        assert!(relay.inbound().at_capacity());
+        assert_eq!(relay.pump_once().unwrap().read, 0);
+    }
+
+    #[test]
+    fn test_pump_waits_for_spent_budget() {
+        let socket = FakeSocket {
+            input: Cursor::new(vec![1, 2, 3]),
+            output: Vec::new(),
+        };
+        let budget = crate::MemoryBudget::new(2);
+        let mut other = RotatingBuffer::try_new(2).unwrap();
+        other.attach_budget(&budget);
+        other.enqueue_slice(&[0, 0]).unwrap();
+
+        let mut relay = Relay::new(socket, 4, 4);
+        relay.inbound().attach_budget(&budget);
+        let stats = relay.pump_once().unwrap();
+        assert_eq!(stats, PumpStats::default());
+        assert!(!relay.is_eof());
+
+        drop(other);
+        assert_eq!(relay.pump_once().unwrap().read, 2);
+        assert!(!relay.is_eof());
+    }
+
+    #[test]
+    fn test_run_echoes() {
+        let socket = FakeSocket {
+            input: Cursor::new(b"hello relay".to_vec()),
+            output: Vec::new(),
+        };
+        let mut relay = Relay::new(socket, 4, 4);
+        relay
+            .run(|inbound, outbound| {
+                while !outbound.at_capacity() {
+                    match inbound.dequeue() {
+                        Some(value) => outbound.enqueue(value).unwrap(),
+                        None => break,
+                    }
+                }
+                Ok(())
+            })
+            .unwrap();
+        assert!(relay.is_eof());
+        assert_eq!(relay.socket().output, b"hello relay");
+    }
+
+    #[test]
+    fn test_run_fails_when_inbound_is_never_drained() {
+        let socket = FakeSocket {
+            input: Cursor::new(vec![1, 2, 3, 4, 5]),
+            output: Vec::new(),
+        };
+        let mut relay = Relay::new(socket, 3, 3);
+        let err = relay.run(|_, _| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Other);
+        assert!(relay.inbound().at_capacity());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_blocking_socket_writes_before_reading() {
+        use std::net::Shutdown;
+        use std::os::unix::net::UnixStream;
+
+        let (socket, mut peer) = UnixStream::pair().unwrap();
+        // The peer only answers once it has received the greeting, so reading before
+        // writing the greeting would block both ends forever.
+        let peer = std::thread::spawn(move || {
+            let mut greeting = [0; 5];
+            peer.read_exact(&mut greeting).unwrap();
+            peer.write_all(b"world").unwrap();
+            peer.shutdown(Shutdown::Write).unwrap();
+            greeting
+        });
+
+        let mut relay = Relay::new(socket, 4, 8);
+        relay.outbound().enqueue_slice(b"hello").unwrap();
+        let mut received = Vec::new();
+        relay
+            .run(|inbound, _| {
+                while let Some(value) = inbound.dequeue() {
+                    received.push(value);
+                }
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(&peer.join().unwrap(), b"hello");
+        assert_eq!(received, b"world");
+    }
+}
@@ -0,0 +1,179 @@
+//! A [RotatingBuffer] of batches tagged with a frame or tick number.
+
+use std::collections::VecDeque;
+
+use bytes::Bytes;
+
+use crate::{IncompleteEnqueue, RotBufError, RotatingBuffer};
+
+/// The [ReplayBuffer] stores batches of bytes, each tagged with a caller-supplied `u64`
+/// (e.g. the frame or tick number they belong to), in a [RotatingBuffer].
+///
+/// When a new batch does not fit, the oldest batches are evicted to make room, so the
+/// [ReplayBuffer] always holds the most recent ticks that fit in its capacity.  Batches
+/// can then be replayed from any retained tag, or trimmed once they are no longer needed.
+///
+/// The ring records the batch boundaries itself (see
+/// [RotatingBuffer::enable_batch_tracking]); the [ReplayBuffer] only adds their tags.
+#[derive(Debug)]
+pub struct ReplayBuffer {
+    ring: RotatingBuffer,
+    /// The tag of every batch in the ring, oldest first.
+    tags: VecDeque<u64>,
+}
+
+impl ReplayBuffer {
+    /// Creates a new [ReplayBuffer] holding up to `capacity` bytes of batches.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if `capacity` is 0.  [ReplayBuffer::try_new] reports that as an error
+    /// instead.
+    pub fn new(capacity: usize) -> Self {
+        match Self::try_new(capacity) {
+            Ok(rb) => rb,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Creates a new [ReplayBuffer] as in [ReplayBuffer::new], returning an [Err] with
+    /// [RotBufError::InvalidCapacity] if `capacity` is 0.
+    pub fn try_new(capacity: usize) -> Result<Self, RotBufError> {
+        let mut ring = RotatingBuffer::try_new(capacity)?;
+        ring.enable_batch_tracking();
+        Ok(Self {
+            ring,
+            tags: VecDeque::new(),
+        })
+    }
+
+    /// Returns the number of batches held.
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Returns whether or not the [ReplayBuffer] holds no batches.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Returns the tag of the oldest batch held, or [None] if empty.
+    pub fn oldest_tag(&self) -> Option<u64> {
+        self.tags.front().copied()
+    }
+
+    /// Returns the tag of the newest batch held, or [None] if empty.
+    pub fn newest_tag(&self) -> Option<u64> {
+        self.tags.back().copied()
+    }
+
+    /// Appends `data` as a batch tagged with `tag`, evicting the oldest batches if needed
+    /// to make room.  Returns an [Err] with an [IncompleteEnqueue] (having written nothing)
+    /// if `data` is larger than the capacity of the [ReplayBuffer].  An empty `data` holds
+    /// no bytes to replay, so no batch is stored for it.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if `tag` is less than the tag of the newest batch, as tags must be pushed in
+    /// non-decreasing order.
+    pub fn push<'a>(&mut self, tag: u64, data: &'a [u8]) -> Result<(), IncompleteEnqueue<'a>> {
+        if let Some(newest) = self.newest_tag() {
+            if tag < newest {
+                panic!(
+                    "Tags must be pushed in non-decreasing order (`{}` after `{}`)",
+                    tag, newest
+                );
+            }
+        }
+        if data.is_empty() {
+            return Ok(());
+        }
+        if data.len() > self.ring.capacity() {
+            return Err(IncompleteEnqueue {
+                written: 0,
+                remainder: data,
+            });
+        }
+        while self.ring.remaining_capacity() < data.len() {
+            self.evict_oldest();
+        }
+        self.ring.enqueue_partial(data);
+        self.tags.push_back(tag);
+        Ok(())
+    }
+
+    /// Removes every batch tagged before `tag`.
+    pub fn trim_before(&mut self, tag: u64) {
+        while self.oldest_tag().is_some_and(|oldest| oldest < tag) {
+            self.evict_oldest();
+        }
+    }
+
+    /// Returns an iterator over the batches tagged `tag` or later, oldest first, yielding
+    /// each tag with a copy of its batch.
+    pub fn replay_from(&self, tag: u64) -> impl Iterator<Item = (u64, Bytes)> + '_ {
+        self.tags
+            .iter()
+            .zip(self.ring.batches())
+            .filter(move |&(&batch_tag, _)| batch_tag >= tag)
+            .map(|(&batch_tag, batch)| (batch_tag, Bytes::copy_from_slice(&batch)))
+    }
+
+    /// Drops the oldest batch from the ring.
+    fn evict_oldest(&mut self) {
+        let lens = self.ring.batch_lens();
+        if let Some(&len) = lens.and_then(VecDeque::front) {
+            self.ring.consume_head(len);
+            self.tags.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn tags(rb: &ReplayBuffer, from: u64) -> Vec<(u64, Vec<u8>)> {
+        rb.replay_from(from)
+            .map(|(tag, batch)| (tag, batch.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_replay_and_trim() {
+        let mut rb = ReplayBuffer::new(8);
+        rb.push(1, &[1, 1]).unwrap();
+        rb.push(2, &[2]).unwrap();
+        rb.push(3, &[3, 3, 3]).unwrap();
+        assert_eq!(tags(&rb, 2), vec![(2, vec![2]), (3, vec![3, 3, 3])]);
+
+        rb.trim_before(3);
+        assert_eq!(rb.oldest_tag(), Some(3));
+        assert_eq!(tags(&rb, 0), vec![(3, vec![3, 3, 3])]);
+    }
+
+    #[test]
+    fn test_evicts_oldest_when_full() {
+        let mut rb = ReplayBuffer::new(4);
+        rb.push(1, &[1, 1]).unwrap();
+        rb.push(2, &[2, 2]).unwrap();
+        rb.push(3, &[3, 3, 3]).unwrap(); // Evicts 1 and 2, wrapping around
+        assert_eq!(rb.len(), 1);
+        assert_eq!(tags(&rb, 0), vec![(3, vec![3, 3, 3])]);
+        assert!(rb.push(4, &[0; 5]).is_err());
+
+        rb.push(4, &[]).unwrap();
+        rb.push(5, &[5, 5]).unwrap(); // Evicts 3
+        assert_eq!(tags(&rb, 0), vec![(5, vec![5, 5])]);
+        assert_eq!(rb.ring.batch_lens().unwrap().len(), rb.len());
+    }
+
+    #[test]
+    #[should_panic(expected = "Tags must be pushed in non-decreasing order")]
+    fn test_push_out_of_order_panics() {
+        let mut rb = ReplayBuffer::new(4);
+        rb.push(2, &[2]).unwrap();
+        rb.push(1, &[1]).unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+//! Run-length encoding at the boundary of a [RotatingBuffer].
+
+use crate::{RotBufError, RotatingBuffer};
+
+impl RotatingBuffer {
+    /// Returns an iterator that dequeues the queue as runs of identical bytes, yielding
+    /// each byte along with the length of its run.
+    ///
+    /// Runs are dequeued lazily, so dropping the iterator early leaves the remaining
+    /// bytes queued.  A run is only split if it is still being enqueued to when drained.
+    pub fn drain_rle(&mut self) -> DrainRle<'_> {
+        DrainRle { rb: self }
+    }
+
+    /// Enqueues `count` copies of `value`, the inverse of a run yielded by
+    /// [RotatingBuffer::drain_rle].  This is [RotatingBuffer::fill], so it is
+    /// all-or-nothing and fails the same way.
+    pub fn enqueue_rle(&mut self, value: u8, count: usize) -> Result<(), RotBufError> {
+        self.fill(value, count)
+    }
+
+    /// Removes consecutive repeated bytes in place, keeping the first byte of each run.
+    /// Returns how many bytes were removed.
+    pub fn dedup_consecutive(&mut self) -> usize {
+        self.dedup_within(1)
+    }
+
+    /// Removes every byte equal to one of the `window` bytes kept before it, keeping the
+    /// order of the rest, and returns how many bytes were removed.  A `window` of 1 is
+    /// [RotatingBuffer::dedup_consecutive], and a `window` of 0 removes nothing.
+    ///
+    /// This takes O(`len * window`) time.
+    pub fn dedup_within(&mut self, window: usize) -> usize {
+        let len = self.len();
+        let mut kept = 0usize;
+        for pos in 0..len {
+            let value = self.buffer[self.get_index(pos).get()];
+            let repeated =
+                (kept.saturating_sub(window)..kept).any(|prev| self.peek_pos(prev) == Some(value));
+            if !repeated {
+                // Swapping rather than copying leaves exactly the removed bytes at the back.
+                let (from, to) = (self.get_index(pos), self.get_index(kept));
+                self.buffer.swap(from.get(), to.get());
+                kept += 1;
+            }
+        }
+        self.truncate_back(len - kept)
+    }
+}
+
+/// An iterator over runs of identical bytes, dequeued from a [RotatingBuffer].  Created by
+/// [RotatingBuffer::drain_rle].
+#[derive(Debug)]
+pub struct DrainRle<'a> {
+    rb: &'a mut RotatingBuffer,
+}
+
+impl Iterator for DrainRle<'_> {
+    type Item = (u8, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.rb.peek()?;
+        let (first, second) = self.rb.segments();
+        let mut run = first.iter().take_while(|&&byte| byte == value).count();
+        if run == first.len() {
+            run += second.iter().take_while(|&&byte| byte == value).count();
+        }
+        self.rb.consume_head(run);
+        Some((value, run))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.rb.len();
+        (len.min(1), Some(len))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_drain_rle_across_wrap() {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        rb.enqueue_partial(&[9, 9, 9, 9]);
+        rb.dequeue();
+        rb.dequeue();
+        rb.enqueue_partial(&[9, 9, 1, 2]); // Wraps around
+        let runs: Vec<_> = rb.drain_rle().collect();
+        assert_eq!(runs, vec![(9, 4), (1, 1), (2, 1)]);
+        assert!(rb.is_empty());
+    }
+
+    #[test]
+    fn test_enqueue_rle_round_trip() {
+        let mut rb = RotatingBuffer::try_new(5).unwrap();
+        rb.enqueue_partial(&[0, 0, 0]);
+        rb.dequeue();
+        rb.dequeue();
+        rb.enqueue_rle(7, 3).unwrap(); // Wraps around
+        assert_eq!(
+            rb.enqueue_rle(8, 2),
+            Err(RotBufError::Incomplete {
+                written: 0,
+                remaining: 2
+            })
+        );
+        rb.enqueue_rle(8, 1).unwrap();
+
+        // Only the first run is dequeued when the iterator is dropped early.
+        assert_eq!(rb.drain_rle().next(), Some((0, 1)));
+        assert_eq!(rb.len(), 4);
+        assert_eq!(rb.drain_rle().collect::<Vec<_>>(), vec![(7, 3), (8, 1)]);
+    }
+
+    #[test]
+    fn test_dedup() {
+        let mut rb = RotatingBuffer::try_new(8).unwrap();
+        rb.enqueue_partial(&[0; 5]);
+        rb.consume_head(5);
+        rb.enqueue_partial(&[1, 1, 2, 2, 2, 1, 3, 3]); // Wraps around
+        assert_eq!(rb.dedup_consecutive(), 4);
+        assert_eq!(rb.last_n(4).to_vec(), vec![1, 2, 1, 3]);
+        assert_eq!(rb.dedup_within(0), 0);
+        assert_eq!(rb.dedup_within(2), 1);
+        assert_eq!(rb.last_n(3).to_vec(), vec![1, 2, 3]);
+
+        rb.enqueue_partial(&[1, 4]);
+        assert_eq!(rb.dedup_within(2), 0);
+        assert_eq!(rb.dedup_within(3), 1);
+        assert_eq!(rb.last_n(4).to_vec(), vec![1, 2, 3, 4]);
+    }
+}
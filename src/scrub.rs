@@ -0,0 +1,50 @@
+//! Zeroization of a [RotatingBuffer]'s backing storage, for buffers that hold secrets.
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::RotatingBuffer;
+
+impl RotatingBuffer {
+    /// Overwrites the whole backing storage with zeros, in a way the compiler will not
+    /// optimize away.
+    pub(crate) fn scrub(&mut self) {
+        self.buffer.as_mut().zeroize();
+    }
+}
+
+/// Empties the [RotatingBuffer] and zeroes its backing storage.  The capacity is kept.
+impl Zeroize for RotatingBuffer {
+    fn zeroize(&mut self) {
+        self.clear();
+    }
+}
+
+impl Drop for RotatingBuffer {
+    fn drop(&mut self) {
+        self.scrub();
+    }
+}
+
+impl ZeroizeOnDrop for RotatingBuffer {}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_zeroize_scrubs_storage() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_partial(&[1, 2, 3]);
+        rb.dequeue();
+        rb.zeroize();
+        assert!(rb.is_empty());
+        assert_eq!(rb.capacity(), 4);
+        assert!(rb.buffer.iter().all(|&byte| byte == 0));
+
+        rb.enqueue_partial(&[4, 5]);
+        rb.resize(8).unwrap();
+        rb.clear();
+        assert!(rb.buffer.iter().all(|&byte| byte == 0));
+    }
+}
@@ -0,0 +1,209 @@
+//! A read-ahead and read-behind cache over a [Read] + [Seek] source.
+
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom};
+
+use crate::{RotBufError, RotatingBuffer};
+
+/// The [SeekBuffered] adaptor keeps a window of a seekable source in a [RotatingBuffer].
+///
+/// Reads fill the ring ahead of the current position, and bytes that have been read stay
+/// in the ring until room is needed for new ones, so small seeks backwards (or forwards
+/// within what has been read ahead) are served from memory without touching the source.
+#[derive(Debug)]
+pub struct SeekBuffered<R> {
+    inner: R,
+    ring: RotatingBuffer,
+    /// The stream offset of the first byte in the ring.
+    window_start: u64,
+    /// The stream offset the caller is at.
+    pos: u64,
+    /// The stream offset `inner` is at.
+    inner_pos: u64,
+}
+
+impl<R: Read + Seek> SeekBuffered<R> {
+    /// Wraps `inner`, which is assumed to be at the start of the stream, with a window of
+    /// `capacity` bytes.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if `capacity` is 0.  [SeekBuffered::try_new] reports that as an error
+    /// instead.
+    pub fn new(inner: R, capacity: usize) -> Self {
+        match Self::try_new(inner, capacity) {
+            Ok(sb) => sb,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Wraps `inner` as in [SeekBuffered::new], returning an [Err] with
+    /// [RotBufError::InvalidCapacity] if `capacity` is 0.
+    pub fn try_new(inner: R, capacity: usize) -> Result<Self, RotBufError> {
+        Ok(Self {
+            inner,
+            ring: RotatingBuffer::try_new(capacity)?,
+            window_start: 0,
+            pos: 0,
+            inner_pos: 0,
+        })
+    }
+
+    /// Returns a reference to the source.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Consumes the [SeekBuffered], returning the source.  Its position is unspecified.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Returns the stream offset one past the last byte in the ring.
+    fn window_end(&self) -> u64 {
+        self.window_start + self.ring.len() as u64
+    }
+
+    /// Discards the window and restarts it at `pos`.
+    fn reset_window(&mut self) {
+        self.ring.consume_head(self.ring.len());
+        self.window_start = self.pos;
+    }
+
+    /// Reads more of the source onto the end of the window, evicting the oldest half of the
+    /// window first if it is full.  Returns how many bytes were read.
+    fn fill(&mut self) -> io::Result<usize> {
+        if self.ring.at_capacity() {
+            let evict = (self.ring.capacity() / 2).max(1);
+            self.ring.consume_head(evict);
+            self.window_start += evict as u64;
+        }
+        let end = self.window_end();
+        if self.inner_pos != end {
+            self.inner_pos = self.inner.seek(SeekFrom::Start(end))?;
+        }
+        let (free, _) = self.ring.free_segments_mut();
+        let read = loop {
+            match self.inner.read(free) {
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                result => break result?,
+            }
+        };
+        self.ring.commit_tail(read);
+        self.inner_pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Read + Seek> Read for SeekBuffered<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.pos < self.window_start || self.pos > self.window_end() {
+            self.reset_window();
+        }
+        if self.pos == self.window_end() && self.fill()? == 0 {
+            return Ok(0);
+        }
+        let start = (self.pos - self.window_start) as usize;
+        let end = self.ring.len().min(start + buf.len());
+        let (first, second) = self.ring.range_segments(start, end);
+        buf[..first.len()].copy_from_slice(first);
+        buf[first.len()..first.len() + second.len()].copy_from_slice(second);
+        self.pos += (end - start) as u64;
+        Ok(end - start)
+    }
+}
+
+impl<R: Read + Seek> Seek for SeekBuffered<R> {
+    fn seek(&mut self, target: SeekFrom) -> io::Result<u64> {
+        let pos = match target {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+            SeekFrom::End(_) => {
+                self.inner_pos = self.inner.seek(target)?;
+                Some(self.inner_pos)
+            }
+        };
+        self.pos = pos.ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            )
+        })?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::io::Cursor;
+
+    /// Counts how many times the source is seeked.
+    struct Counting {
+        inner: Cursor<Vec<u8>>,
+        seeks: usize,
+    }
+
+    impl Read for Counting {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for Counting {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.seeks += 1;
+            self.inner.seek(pos)
+        }
+    }
+
+    fn source() -> Counting {
+        Counting {
+            inner: Cursor::new((0..100).collect()),
+            seeks: 0,
+        }
+    }
+
+    #[test]
+    fn test_small_backward_seeks_stay_in_memory() {
+        let mut sb = SeekBuffered::new(source(), 16);
+        let mut buf = [0; 4];
+        sb.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0, 1, 2, 3]);
+        sb.seek(SeekFrom::Current(-3)).unwrap();
+        sb.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [1, 2, 3, 4]);
+        sb.seek(SeekFrom::Start(12)).unwrap();
+        sb.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [12, 13, 14, 15]);
+        assert_eq!(sb.get_ref().seeks, 0);
+
+        // Reading on past the window evicts its oldest half, but keeps recent bytes.
+        sb.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [16, 17, 18, 19]);
+        sb.seek(SeekFrom::Start(10)).unwrap();
+        sb.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [10, 11, 12, 13]);
+        assert_eq!(sb.get_ref().seeks, 0);
+    }
+
+    #[test]
+    fn test_far_seeks_and_end() {
+        let mut sb = SeekBuffered::new(source(), 8);
+        let mut buf = [0; 2];
+        sb.seek(SeekFrom::Start(50)).unwrap();
+        sb.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [50, 51]);
+        assert_eq!(sb.seek(SeekFrom::End(-1)).unwrap(), 99);
+        let mut rest = Vec::new();
+        sb.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, vec![99]);
+        sb.seek(SeekFrom::Start(0)).unwrap();
+        sb.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0, 1]);
+        assert!(sb.seek(SeekFrom::Current(-3)).is_err());
+    }
+}
@@ -0,0 +1,541 @@
+//! A [RotatingBuffer] that spools to disk once a memory threshold is exceeded.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "encryption")]
+use crate::encryption::SpoolCipher;
+use crate::{RotBufError, RotatingBuffer};
+
+/// The number of spooled bytes staged in memory before they are written to the spool file.
+const STAGING_LEN: usize = 4096;
+
+/// Used to give every spool file created by this process a unique name.
+static SPOOL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// The [SpillingBuffer] is a queue that keeps up to `threshold` bytes in an in-memory
+/// [RotatingBuffer] and spools anything beyond that to a temporary file.
+///
+/// Ordering is preserved: once data has been spooled, every subsequent byte is spooled
+/// as well until the spool has been read back.  Dequeueing transparently refills the
+/// in-memory ring from the spool file, and the file is removed when the
+/// [SpillingBuffer] is dropped.
+///
+/// Spooled bytes are written to the file as they are unless
+/// [SpillingBuffer::compress_spool] or, with the `encryption` feature,
+/// `SpillingBuffer::encrypt_spool` is used.
+#[derive(Debug)]
+pub struct SpillingBuffer {
+    /// The in-memory ring holding the oldest bytes.
+    ring: RotatingBuffer,
+    /// How many bytes may be held in memory before spooling to disk.
+    threshold: usize,
+    /// The directory spool files are created in.
+    dir: PathBuf,
+    /// How spooled bytes are encoded.
+    options: SpoolOptions,
+    /// The spool file, created the first time we spill.
+    spool: Option<Spool>,
+}
+
+/// How a [SpillingBuffer] compresses the bytes it spools to disk.  Set with
+/// [SpillingBuffer::compress_spool].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum SpoolCompression {
+    /// Spooled bytes are written as they are.
+    #[default]
+    None,
+    /// Spooled bytes are compressed with raw deflate, at a level from 0 (no compression) to
+    /// 9 (best).
+    #[cfg(feature = "flate2")]
+    Deflate(u32),
+    /// Spooled bytes are compressed with zstd at the given level, where 0 uses zstd's
+    /// default level.
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+impl SpoolCompression {
+    fn compress(self, block: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            SpoolCompression::None => Ok(block.to_vec()),
+            #[cfg(feature = "flate2")]
+            SpoolCompression::Deflate(level) => {
+                let mut encoder =
+                    flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::new(level));
+                encoder.write_all(block)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "zstd")]
+            SpoolCompression::Zstd(level) => zstd::bulk::compress(block, level),
+        }
+    }
+
+    fn decompress(self, record: Vec<u8>) -> io::Result<Vec<u8>> {
+        match self {
+            SpoolCompression::None => Ok(record),
+            #[cfg(feature = "flate2")]
+            SpoolCompression::Deflate(_) => {
+                let mut block = Vec::with_capacity(STAGING_LEN);
+                flate2::read::DeflateDecoder::new(&record[..]).read_to_end(&mut block)?;
+                Ok(block)
+            }
+            #[cfg(feature = "zstd")]
+            SpoolCompression::Zstd(_) => zstd::stream::decode_all(&record[..]),
+        }
+    }
+}
+
+/// How the blocks of a [Spool] are encoded into records.
+#[derive(Debug, Clone, Default)]
+struct SpoolOptions {
+    compression: SpoolCompression,
+    #[cfg(feature = "encryption")]
+    cipher: Option<SpoolCipher>,
+}
+
+impl SpoolOptions {
+    /// Compresses, then encrypts, the `seq`th block written to a spool.
+    #[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+    fn encode(&self, seq: u64, block: &[u8]) -> io::Result<Vec<u8>> {
+        #[cfg_attr(not(feature = "encryption"), allow(unused_mut))]
+        let mut record = self.compression.compress(block)?;
+        #[cfg(feature = "encryption")]
+        if let Some(cipher) = &self.cipher {
+            cipher.seal(seq, &mut record);
+        }
+        Ok(record)
+    }
+
+    /// Decrypts, then decompresses, the `seq`th record read back from a spool.
+    #[cfg_attr(not(feature = "encryption"), allow(unused_variables, unused_mut))]
+    fn decode(&self, seq: u64, mut record: Vec<u8>) -> io::Result<Vec<u8>> {
+        #[cfg(feature = "encryption")]
+        if let Some(cipher) = &self.cipher {
+            cipher.open(seq, &mut record)?;
+        }
+        self.compression.decompress(record)
+    }
+}
+
+/// The on-disk portion of a [SpillingBuffer].
+///
+/// Spooled bytes are staged in blocks of up to [STAGING_LEN] bytes, and each block is
+/// written to the file as a record: its encoded length as a `u32` (LE), then the block as
+/// encoded by the [SpoolOptions].  Records are read back whole.
+#[derive(Debug)]
+struct Spool {
+    file: File,
+    path: PathBuf,
+    options: SpoolOptions,
+    /// The number of records written to and read back from the file, which number them
+    /// for encryption.
+    records_written: u64,
+    records_read: u64,
+    /// Offset of the next record to read back from the file.
+    read_pos: u64,
+    /// Offset of the end of the records written to the file.
+    write_pos: u64,
+    /// Bytes spooled but not yet written to the file.
+    staging: Vec<u8>,
+    /// The block read back last, of which the bytes from `unread_pos` on are still to be
+    /// pulled.
+    unread: Vec<u8>,
+    unread_pos: usize,
+    /// The number of spooled bytes waiting, wherever they are.
+    len: u64,
+}
+
+impl Spool {
+    fn create(dir: &Path, options: SpoolOptions) -> io::Result<Self> {
+        let path = dir.join(format!(
+            "rotbuf-spill-{}-{}",
+            std::process::id(),
+            SPOOL_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(Self {
+            file,
+            path,
+            options,
+            records_written: 0,
+            records_read: 0,
+            read_pos: 0,
+            write_pos: 0,
+            staging: Vec::new(),
+            unread: Vec::new(),
+            unread_pos: 0,
+            len: 0,
+        })
+    }
+
+    /// Returns the number of bytes waiting in the spool.
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn push(&mut self, mut src: &[u8]) -> io::Result<()> {
+        while !src.is_empty() {
+            let n = (STAGING_LEN - self.staging.len()).min(src.len());
+            self.staging.extend_from_slice(&src[..n]);
+            self.len += n as u64;
+            src = &src[n..];
+            if self.staging.len() == STAGING_LEN {
+                self.flush_staging()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn flush_staging(&mut self) -> io::Result<()> {
+        let record = self.options.encode(self.records_written, &self.staging)?;
+        let record_len = u32::try_from(record.len())
+            .map_err(|_| io::Error::new(ErrorKind::InvalidData, "spool record too long"))?;
+        self.file.seek(SeekFrom::Start(self.write_pos))?;
+        self.file.write_all(&record_len.to_le_bytes())?;
+        self.file.write_all(&record)?;
+        self.write_pos += 4 + record.len() as u64;
+        self.records_written += 1;
+        self.staging.clear();
+        Ok(())
+    }
+
+    /// Moves the next block into `unread`: the oldest record in the file, or the staged
+    /// bytes if the file has been read back entirely.
+    ///
+    /// If reading or decoding the record fails, nothing moves: the exhausted block is left
+    /// in place, so that no byte is pulled twice, and the record is read again next time
+    /// under the same sequence number.
+    fn next_block(&mut self) -> io::Result<()> {
+        if self.read_pos == self.write_pos {
+            self.unread = std::mem::take(&mut self.staging);
+            self.unread_pos = 0;
+            return Ok(());
+        }
+        self.file.seek(SeekFrom::Start(self.read_pos))?;
+        let mut record_len = [0; 4];
+        self.file.read_exact(&mut record_len)?;
+        let mut record = vec![0; u32::from_le_bytes(record_len) as usize];
+        self.file.read_exact(&mut record)?;
+        let record_end = self.read_pos + 4 + record.len() as u64;
+        self.unread = self.options.decode(self.records_read, record)?;
+        self.unread_pos = 0;
+        self.read_pos = record_end;
+        self.records_read += 1;
+
+        // Once everything has been read back, reclaim the disk space.
+        if self.read_pos == self.write_pos {
+            self.file.set_len(0)?;
+            self.read_pos = 0;
+            self.write_pos = 0;
+        }
+        Ok(())
+    }
+
+    /// Reads spooled bytes into `dst`, returning how many were read.
+    fn pull(&mut self, dst: &mut [u8]) -> io::Result<usize> {
+        if self.unread_pos == self.unread.len() {
+            self.next_block()?;
+        }
+        let unread = &self.unread[self.unread_pos..];
+        let n = dst.len().min(unread.len());
+        dst[..n].copy_from_slice(&unread[..n]);
+        self.unread_pos += n;
+        self.len -= n as u64;
+        Ok(n)
+    }
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl SpillingBuffer {
+    /// Creates a new [SpillingBuffer] holding at most `threshold` bytes in memory and
+    /// spooling to files in the system temporary directory.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if `threshold` is 0.  [SpillingBuffer::try_new] reports that as an error
+    /// instead.
+    pub fn new(threshold: usize) -> Self {
+        Self::with_dir(threshold, std::env::temp_dir())
+    }
+
+    /// Creates a new [SpillingBuffer] as in [SpillingBuffer::new], returning an [Err] with
+    /// [RotBufError::InvalidCapacity] if `threshold` is 0.
+    pub fn try_new(threshold: usize) -> Result<Self, RotBufError> {
+        Self::try_with_dir(threshold, std::env::temp_dir())
+    }
+
+    /// Creates a new [SpillingBuffer] that spools to files in `dir`.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if `threshold` is 0.  [SpillingBuffer::try_with_dir] reports that as an
+    /// error instead.
+    pub fn with_dir(threshold: usize, dir: impl Into<PathBuf>) -> Self {
+        match Self::try_with_dir(threshold, dir) {
+            Ok(sb) => sb,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Creates a new [SpillingBuffer] as in [SpillingBuffer::with_dir], returning an [Err]
+    /// with [RotBufError::InvalidCapacity] if `threshold` is 0.
+    pub fn try_with_dir(threshold: usize, dir: impl Into<PathBuf>) -> Result<Self, RotBufError> {
+        Ok(Self {
+            ring: RotatingBuffer::try_new(threshold)?,
+            threshold,
+            dir: dir.into(),
+            options: SpoolOptions::default(),
+            spool: None,
+        })
+    }
+
+    /// Sets how the bytes spooled to disk are compressed, which can greatly reduce the
+    /// disk space and I/O taken by compressible data.  Nothing is compressed by default.
+    pub fn compress_spool(mut self, compression: SpoolCompression) -> Self {
+        self.options.compression = compression;
+        self
+    }
+
+    /// Encrypts the bytes spooled to disk with AES-256-GCM under `key`, so that they never
+    /// reach the spool file in plaintext.  Blocks are compressed before being encrypted.
+    ///
+    /// A spool record that was tampered with, reordered or replayed fails to read back,
+    /// and the dequeue reports it as [ErrorKind::InvalidData].  The spool does not skip
+    /// past such a record, so every later dequeue fails the same way.
+    #[cfg(feature = "encryption")]
+    pub fn encrypt_spool(mut self, key: &[u8; 32]) -> Self {
+        self.options.cipher = Some(SpoolCipher::new(key));
+        self
+    }
+
+    /// Returns the in-memory threshold.
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Returns the total number of bytes queued, in memory and on disk.
+    pub fn len(&self) -> usize {
+        self.ring.len() + self.spilled_len() as usize
+    }
+
+    /// Returns whether or not the [SpillingBuffer] is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of bytes currently spooled to disk.
+    pub fn spilled_len(&self) -> u64 {
+        self.spool.as_ref().map_or(0, Spool::len)
+    }
+
+    /// Enqueues a byte, spooling it to disk if the in-memory ring is full.
+    pub fn enqueue(&mut self, value: u8) -> io::Result<()> {
+        self.enqueue_slice(&[value])
+    }
+
+    /// Enqueues every byte of `src`, spooling whatever does not fit in memory.
+    pub fn enqueue_slice(&mut self, mut src: &[u8]) -> io::Result<()> {
+        if self.spilled_len() == 0 {
+            let written = self.ring.enqueue_partial(src);
+            src = &src[written..];
+        }
+        if src.is_empty() {
+            return Ok(());
+        }
+        let spool = match &mut self.spool {
+            Some(spool) => spool,
+            None => self
+                .spool
+                .insert(Spool::create(&self.dir, self.options.clone())?),
+        };
+        spool.push(src)
+    }
+
+    /// Dequeues the oldest byte, reading it back from disk if necessary.  Returns
+    /// `Ok(None)` if the [SpillingBuffer] is empty.
+    pub fn dequeue(&mut self) -> io::Result<Option<u8>> {
+        if self.ring.is_empty() {
+            self.refill()?;
+        }
+        Ok(self.ring.dequeue())
+    }
+
+    /// Moves spooled bytes back into the in-memory ring.
+    fn refill(&mut self) -> io::Result<()> {
+        let Some(spool) = &mut self.spool else {
+            return Ok(());
+        };
+        let (first, second) = self.ring.free_segments_mut();
+        let mut read = spool.pull(first)?;
+        // Bytes already pulled have left the spool, so keep them even if the second pull
+        // fails.
+        let result = match read == first.len() {
+            true => spool.pull(second).map(|n| read += n),
+            false => Ok(()),
+        };
+        self.ring.commit_tail(read);
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_spills_and_preserves_order() {
+        let mut sb = SpillingBuffer::new(4);
+        sb.enqueue_slice(&[1, 2, 3, 4, 5, 6, 7]).unwrap();
+        assert_eq!(sb.spilled_len(), 3);
+        assert_eq!(sb.len(), 7);
+        sb.enqueue(8).unwrap();
+        for expected in 1..=8 {
+            assert_eq!(sb.dequeue().unwrap(), Some(expected));
+        }
+        assert_eq!(sb.dequeue().unwrap(), None);
+        assert!(sb.is_empty());
+    }
+
+    #[test]
+    fn test_try_new_rejects_zero_threshold() {
+        assert_eq!(
+            SpillingBuffer::try_new(0).unwrap_err(),
+            RotBufError::InvalidCapacity(0)
+        );
+    }
+
+    #[test]
+    fn test_spill_file_removed_on_drop() {
+        let mut sb = SpillingBuffer::new(3);
+        sb.enqueue_slice(&[0; 10]).unwrap();
+        let path = sb.spool.as_ref().unwrap().path.clone();
+        assert!(path.exists());
+        drop(sb);
+        assert!(!path.exists());
+    }
+
+    /// Spools compressible data through `sb`, returning the spool file's contents once
+    /// everything has been enqueued.
+    fn spill_round_trip(mut sb: SpillingBuffer) -> Vec<u8> {
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i / 100) as u8).collect();
+        for chunk in data.chunks(3000) {
+            sb.enqueue_slice(chunk).unwrap();
+        }
+        let file = fs::read(&sb.spool.as_ref().unwrap().path).unwrap();
+        let mut out = Vec::new();
+        while let Some(value) = sb.dequeue().unwrap() {
+            out.push(value);
+        }
+        assert_eq!(out, data);
+        file
+    }
+
+    #[test]
+    fn test_spool_records() {
+        // The 19984 spooled bytes are 4 full blocks written with their lengths, and the
+        // rest still staged.
+        let file = spill_round_trip(SpillingBuffer::new(16));
+        assert_eq!(file.len(), 4 * (4 + 4096));
+    }
+
+    #[test]
+    fn test_refill_keeps_bytes_pulled_before_a_failed_read() {
+        let mut sb = SpillingBuffer::new(6);
+        sb.enqueue_slice(&[0; 2]).unwrap();
+        sb.dequeue().unwrap();
+        sb.dequeue().unwrap();
+        // With the ring's head at 2, every refill pulls 4 bytes and then 2, so the first
+        // record runs out at the end of a first pull.
+        let data: Vec<u8> = (0..6 + 2 * 4096u32).map(|i| (i % 251) as u8).collect();
+        sb.enqueue_slice(&data).unwrap();
+        sb.spool
+            .as_ref()
+            .unwrap()
+            .file
+            .set_len(4 + 4096 + 2)
+            .unwrap();
+
+        let mut out = Vec::new();
+        let mut errors = 0;
+        while errors < 2 {
+            match sb.dequeue() {
+                Ok(Some(value)) => out.push(value),
+                Ok(None) => break,
+                Err(err) => {
+                    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+                    errors += 1;
+                }
+            }
+        }
+        assert_eq!(out.len(), 6 + 4096);
+        assert_eq!(out, data[..6 + 4096]);
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn test_deflate_spool() {
+        let sb = SpillingBuffer::new(16).compress_spool(SpoolCompression::Deflate(6));
+        assert!(spill_round_trip(sb).len() < 1000);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_spool() {
+        let sb = SpillingBuffer::new(16).compress_spool(SpoolCompression::Zstd(0));
+        assert!(spill_round_trip(sb).len() < 1000);
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_encrypted_spool() {
+        let file = spill_round_trip(SpillingBuffer::new(16).encrypt_spool(&[7; 32]));
+        // Each record grows by its tag and nonce.
+        assert_eq!(file.len(), 4 * (4 + 4096 + 16 + 12));
+        // The plaintext is made of runs of 100 equal bytes, which would show up in a
+        // plaintext spool.
+        assert!(!file.windows(32).any(|w| w.iter().all(|&b| b == w[0])));
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_encrypted_spool_detects_tampering() {
+        let mut sb = SpillingBuffer::new(4).encrypt_spool(&[7; 32]);
+        sb.enqueue_slice(&[1; 4 + 2 * 4096]).unwrap();
+        let spool = sb.spool.as_mut().unwrap();
+        let mut byte = [0];
+        spool.file.seek(SeekFrom::Start(10)).unwrap();
+        spool.file.read_exact(&mut byte).unwrap();
+        spool.file.seek(SeekFrom::Start(10)).unwrap();
+        spool.file.write_all(&[byte[0] ^ 1]).unwrap();
+
+        for _ in 0..4 {
+            assert_eq!(sb.dequeue().unwrap(), Some(1));
+        }
+        let err = sb.dequeue().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(sb.dequeue().unwrap_err().kind(), ErrorKind::InvalidData);
+
+        // Undoing the tampering lets the same record, and every later one, be read.
+        let spool = sb.spool.as_mut().unwrap();
+        spool.file.seek(SeekFrom::Start(10)).unwrap();
+        spool.file.write_all(&byte).unwrap();
+        for _ in 0..2 * 4096 {
+            assert_eq!(sb.dequeue().unwrap(), Some(1));
+        }
+        assert_eq!(sb.dequeue().unwrap(), None);
+    }
+}
@@ -0,0 +1,342 @@
+//! Exporting and importing the full state of a [RotatingBuffer].
+//!
+//! The exported state is a small header followed by the queued bytes:
+//!
+//! | Field          | Encoding                                   |
+//! |----------------|--------------------------------------------|
+//! | magic          | `b"RBST"`                                  |
+//! | version        | `u16` (LE)                                 |
+//! | capacity       | `u64` (LE)                                 |
+//! | head index     | `u64` (LE)                                 |
+//! | length         | `u64` (LE)                                 |
+//! | enqueue offset | `u64` (LE)                                 |
+//! | overflow       | `u8`: 0 for drop newest, 1 for drop oldest |
+//! | flags          | `u8`: bit 0 set to zero on dequeue         |
+//! | contents       | `length` bytes                             |
+//!
+//! The dequeue offset is not stored, as it is always the enqueue offset minus the length.
+//!
+//! Only the queue and the settings above are carried.  The label, running CRCs, attached
+//! digest, batch boundaries and memory budget are process-local and are not exported;
+//! re-enable them on the imported buffer as needed.  Cursor positions are not carried
+//! either (see [RotatingBuffer::export_state]).
+//!
+//! # Versions
+//!
+//! [RotatingBuffer::export_state] always writes [STATE_FORMAT_VERSION], and
+//! [RotatingBuffer::import_state] rejects any version it does not know:
+//!
+//! * **Version 1** is the current format described above, and the first one released.
+//!
+//! As version 1 is the only version so far, there is nothing to migrate from yet.  When
+//! the layout changes, the version will be bumped and [RotatingBuffer::import_state] will
+//! gain a decoder for version 1 alongside the new one, so that states written by earlier
+//! releases remain loadable.
+
+use std::io::{self, Read, Write};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::index::Capacity;
+use crate::{OverflowPolicy, RotBufError, RotatingBuffer};
+
+/// The version of the state format written by [RotatingBuffer::export_state].
+pub const STATE_FORMAT_VERSION: u16 = 1;
+
+/// Marks the start of a versioned exported state.
+const MAGIC: &[u8; 4] = b"RBST";
+
+/// The length of the capacity, head, length, enqueue offset, overflow and flags fields.
+const FIELDS_LEN: usize = 4 * 8 + 2;
+
+/// The bit of the flags field recording [RotatingBuffer::zero_on_dequeue].
+const ZERO_ON_DEQUEUE: u8 = 1;
+
+/// The length of the header preceding the contents.
+const HEADER_LEN: usize = MAGIC.len() + 2 + FIELDS_LEN;
+
+/// An exported state, independent of the version it was encoded with.
+struct DecodedState<'a> {
+    capacity: usize,
+    head: usize,
+    enqueue_offset: u64,
+    overflow_policy: OverflowPolicy,
+    zero_on_dequeue: bool,
+    contents: &'a [u8],
+}
+
+impl RotatingBuffer {
+    /// Exports the contents of the [RotatingBuffer] along with its capacity, ring and
+    /// stream offsets and settings, so that [RotatingBuffer::import_state] can recreate an
+    /// identical buffer (for example, in the next generation of a process during a
+    /// restart).  The label, running CRCs, attached digest, batch boundaries and memory
+    /// budget are not carried over.
+    ///
+    /// Consumer positions are not carried either: [crate::RotBufCursor]s and
+    /// [crate::PeekConsumer]s borrow the buffer, so none can be registered with it or
+    /// outlive an export.  To resume a reader after a restart, save its position as a
+    /// stream offset ([RotatingBuffer::dequeue_offset] plus the position) next to the
+    /// state, and after importing, [seek](crate::RotBufCursor::seek) a new cursor to that
+    /// offset minus the imported dequeue offset.
+    pub fn export_state(&self) -> Bytes {
+        let mut out = BytesMut::with_capacity(self.state_len());
+        self.write_state(&mut out);
+        out.freeze()
+    }
+
+    /// Returns the length of the state written by [RotatingBuffer::export_state].
+    pub(crate) fn state_len(&self) -> usize {
+        HEADER_LEN + self.len()
+    }
+
+    /// Writes the state exported by [RotatingBuffer::export_state] to `out`.
+    pub(crate) fn write_state(&self, out: &mut impl BufMut) {
+        out.put_slice(MAGIC);
+        out.put_u16_le(STATE_FORMAT_VERSION);
+        out.put_u64_le(self.capacity() as u64);
+        out.put_u64_le(self.head().get() as u64);
+        out.put_u64_le(self.len() as u64);
+        out.put_u64_le(self.enqueue_offset);
+        out.put_u8(match self.overflow_policy {
+            OverflowPolicy::DropNewest => 0,
+            OverflowPolicy::DropOldest => 1,
+        });
+        out.put_u8(if self.zero_on_dequeue {
+            ZERO_ON_DEQUEUE
+        } else {
+            0
+        });
+        let (first, second) = self.segments();
+        out.put_slice(first);
+        out.put_slice(second);
+    }
+
+    /// Recreates a [RotatingBuffer] from a state produced by [RotatingBuffer::export_state]
+    /// with [STATE_FORMAT_VERSION] or, in later releases, any earlier version.  The queued bytes are restored at the
+    /// same ring and stream offsets they were exported from.
+    ///
+    /// Returns an [Err] with a [RotBufError::InvalidState] if `state` is malformed, was
+    /// written by a newer version of the crate, or declares a capacity too large to
+    /// allocate.
+    pub fn import_state(state: &[u8]) -> Result<Self, RotBufError> {
+        let decoded = decode(state)?;
+
+        let size = Capacity::new(decoded.capacity).ok_or(RotBufError::InvalidState(
+            "capacity is not valid for a RotatingBuffer",
+        ))?;
+        // The capacity comes from untrusted input, so allocate fallibly rather than abort.
+        // [BytesMut] has no fallible allocation, so allocate a [Vec], which it adopts
+        // without copying.
+        let mut buffer = Vec::new();
+        buffer
+            .try_reserve_exact(decoded.capacity)
+            .map_err(|_| RotBufError::InvalidState("capacity is too large to allocate"))?;
+        let mut rb = RotatingBuffer::with_buffer(BytesMut::from(Bytes::from(buffer)), size);
+        let head = rb
+            .size
+            .index(decoded.head)
+            .ok_or(RotBufError::InvalidState("head is outside of the buffer"))?;
+        rb.head = head;
+        rb.tail = head;
+        rb.enqueue_partial(decoded.contents);
+        rb.enqueue_offset = decoded.enqueue_offset;
+        rb.dequeue_offset = decoded.enqueue_offset - decoded.contents.len() as u64;
+        rb.overflow_policy = decoded.overflow_policy;
+        rb.zero_on_dequeue = decoded.zero_on_dequeue;
+        Ok(rb)
+    }
+
+    /// Writes the state of the [RotatingBuffer] (see [RotatingBuffer::export_state]) to
+    /// `writer`.
+    pub fn save_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.export_state())
+    }
+
+    /// Reads a state from `reader` until end-of-stream and imports it (see
+    /// [RotatingBuffer::import_state]).
+    ///
+    /// Malformed states are reported as [io::ErrorKind::InvalidData].
+    pub fn load_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut state = Vec::new();
+        reader.read_to_end(&mut state)?;
+        RotatingBuffer::import_state(&state)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/// Decodes an exported state of any supported version.
+fn decode(mut state: &[u8]) -> Result<DecodedState<'_>, RotBufError> {
+    if state.len() < MAGIC.len() {
+        return Err(RotBufError::InvalidState(
+            "state is shorter than its header",
+        ));
+    }
+    let (magic, rest) = state.split_at(MAGIC.len());
+    if magic != MAGIC {
+        return Err(RotBufError::InvalidState(
+            "state does not start with the expected magic",
+        ));
+    }
+    state = rest;
+    if state.len() < 2 {
+        return Err(RotBufError::InvalidState(
+            "state is shorter than its header",
+        ));
+    }
+    match state.get_u16_le() {
+        1 => decode_fields(state),
+        _ => Err(RotBufError::InvalidState(
+            "state was written by an unsupported version",
+        )),
+    }
+}
+
+/// Decodes the fields and contents of a version 1 state.
+fn decode_fields(mut state: &[u8]) -> Result<DecodedState<'_>, RotBufError> {
+    if state.len() < FIELDS_LEN {
+        return Err(RotBufError::InvalidState(
+            "state is shorter than its header",
+        ));
+    }
+    let capacity = usize::try_from(state.get_u64_le())
+        .map_err(|_| RotBufError::InvalidState("capacity does not fit in a usize"))?;
+    let head = usize::try_from(state.get_u64_le())
+        .map_err(|_| RotBufError::InvalidState("head does not fit in a usize"))?;
+    let len = usize::try_from(state.get_u64_le())
+        .map_err(|_| RotBufError::InvalidState("length does not fit in a usize"))?;
+    let enqueue_offset = state.get_u64_le();
+    let overflow_policy = match state.get_u8() {
+        0 => OverflowPolicy::DropNewest,
+        1 => OverflowPolicy::DropOldest,
+        _ => {
+            return Err(RotBufError::InvalidState(
+                "overflow policy is not recognized",
+            ))
+        }
+    };
+    let flags = state.get_u8();
+    if flags & !ZERO_ON_DEQUEUE != 0 {
+        return Err(RotBufError::InvalidState("flags are not recognized"));
+    }
+
+    if len > capacity {
+        return Err(RotBufError::InvalidState("length exceeds the capacity"));
+    }
+    if enqueue_offset < len as u64 {
+        return Err(RotBufError::InvalidState(
+            "length exceeds the enqueue offset",
+        ));
+    }
+    if state.len() != len {
+        return Err(RotBufError::InvalidState(
+            "contents do not match the recorded length",
+        ));
+    }
+    Ok(DecodedState {
+        capacity,
+        head,
+        enqueue_offset,
+        overflow_policy,
+        zero_on_dequeue: flags & ZERO_ON_DEQUEUE != 0,
+        contents: state,
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue(1).unwrap();
+        rb.enqueue(2).unwrap();
+        rb.enqueue(3).unwrap();
+        rb.dequeue();
+        rb.enqueue(4).unwrap();
+        rb.enqueue(5).unwrap(); // Wraps around
+
+        rb.set_overflow_policy(OverflowPolicy::DropOldest);
+        rb.set_zero_on_dequeue(true);
+
+        let mut imported = RotatingBuffer::import_state(&rb.export_state()).unwrap();
+        assert_eq!(imported.capacity(), 4);
+        assert_eq!(imported.head(), rb.head());
+        assert_eq!(imported.tail(), rb.tail());
+        assert_eq!(imported.enqueue_offset(), 5);
+        assert_eq!(imported.dequeue_offset(), 1);
+        assert_eq!(imported.overflow_policy(), OverflowPolicy::DropOldest);
+        assert!(imported.zero_on_dequeue());
+        assert!(imported.at_capacity());
+        assert_eq!(
+            imported.buffer.capacity(),
+            4,
+            "allocated once, at the capacity"
+        );
+        for expected in 2..=5 {
+            assert_eq!(imported.dequeue(), Some(expected));
+        }
+        assert!(imported.is_empty());
+    }
+
+    #[test]
+    fn test_resume_cursor_from_stream_offset() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue_slice(&[1, 2, 3]).unwrap();
+        rb.dequeue();
+        rb.enqueue_slice(&[4, 5]).unwrap();
+        let mut cursor = rb.cursor();
+        cursor.read_u8().unwrap();
+        let saved = rb.dequeue_offset() + cursor.position() as u64;
+        let state = rb.export_state();
+
+        let imported = RotatingBuffer::import_state(&state).unwrap();
+        let mut cursor = imported.cursor();
+        cursor
+            .seek((saved - imported.dequeue_offset()) as usize)
+            .unwrap();
+        assert_eq!(cursor.read_u8(), Ok(3));
+    }
+
+    #[test]
+    fn test_import_rejects_bad_state() {
+        let state = RotatingBuffer::try_new(3).unwrap().export_state();
+        assert!(RotatingBuffer::import_state(&state[..4]).is_err());
+
+        let mut bad_magic = state.to_vec();
+        bad_magic[0] = b'X';
+        assert!(RotatingBuffer::import_state(&bad_magic).is_err());
+
+        let mut extra = state.to_vec();
+        extra.push(0);
+        assert!(RotatingBuffer::import_state(&extra).is_err());
+
+        let mut future = state.to_vec();
+        future[MAGIC.len()] = 2;
+        assert!(RotatingBuffer::import_state(&future).is_err());
+    }
+
+    #[test]
+    fn test_import_rejects_huge_capacity() {
+        let mut huge = RotatingBuffer::try_new(3).unwrap().export_state().to_vec();
+        huge[MAGIC.len() + 2..MAGIC.len() + 10].copy_from_slice(&(1u64 << 60).to_le_bytes());
+        assert_eq!(
+            RotatingBuffer::import_state(&huge).unwrap_err(),
+            RotBufError::InvalidState("capacity is too large to allocate")
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
+        rb.enqueue(9).unwrap();
+        let mut saved = Vec::new();
+        rb.save_to(&mut saved).unwrap();
+
+        let mut loaded = RotatingBuffer::load_from(saved.as_slice()).unwrap();
+        assert_eq!(loaded.dequeue(), Some(9));
+        let err = RotatingBuffer::load_from(&b"nope"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,151 @@
+//! Rate-limited draining of a [RotatingBuffer].
+
+use std::io::{self, ErrorKind, Write};
+use std::time::Instant;
+
+use crate::RotatingBuffer;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// The [Throttle] is a token bucket that limits how quickly bytes are drained from a
+/// [RotatingBuffer] into a sink.
+///
+/// Tokens accrue at `bytes_per_sec` up to the burst size, and every byte written to the
+/// sink spends one.  The current time is passed in by the caller, so the [Throttle] can be
+/// driven by any event loop (and deterministically in tests).
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    bytes_per_sec: u64,
+    burst: usize,
+    /// Whole bytes that may be written right now.
+    tokens: usize,
+    /// Fractional tokens, in byte-nanoseconds, carried between refills.
+    carry: u128,
+    /// When tokens were last refilled.
+    last_refill: Option<Instant>,
+}
+
+impl Throttle {
+    /// Creates a new [Throttle] releasing at most `bytes_per_sec`, with a burst size of one
+    /// second's worth of bytes.  The bucket starts full.
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self::with_burst(
+            bytes_per_sec,
+            usize::try_from(bytes_per_sec).unwrap_or(usize::MAX),
+        )
+    }
+
+    /// Creates a new [Throttle] releasing at most `bytes_per_sec`, allowing up to `burst`
+    /// bytes to be released at once after an idle period.  The bucket starts full.
+    pub fn with_burst(bytes_per_sec: u64, burst: usize) -> Self {
+        Self {
+            bytes_per_sec,
+            burst,
+            tokens: burst,
+            carry: 0,
+            last_refill: None,
+        }
+    }
+
+    /// Returns how many bytes may currently be released, as of the last refill.
+    pub fn available(&self) -> usize {
+        self.tokens
+    }
+
+    /// Adds the tokens earned since the last refill.
+    fn refill(&mut self, now: Instant) {
+        if let Some(last) = self.last_refill {
+            let elapsed = now.saturating_duration_since(last).as_nanos();
+            let earned = elapsed * self.bytes_per_sec as u128 + self.carry;
+            let whole = earned / NANOS_PER_SEC;
+            let tokens = (self.tokens as u128).saturating_add(whole);
+            if tokens >= self.burst as u128 {
+                self.tokens = self.burst;
+                self.carry = 0;
+            } else {
+                self.tokens = tokens as usize;
+                self.carry = earned % NANOS_PER_SEC;
+            }
+        }
+        self.last_refill = Some(now);
+    }
+
+    /// Writes as many queued bytes from `rb` into `sink` as the budget at `now` allows,
+    /// dequeueing what the sink accepted.  Returns the number of bytes written.
+    ///
+    /// `WouldBlock` and `Interrupted` from the sink end the drain early without an error.
+    pub fn drain<W: Write>(
+        &mut self,
+        rb: &mut RotatingBuffer,
+        sink: &mut W,
+        now: Instant,
+    ) -> io::Result<usize> {
+        self.refill(now);
+        let mut written = 0;
+        while self.tokens > 0 && !rb.is_empty() {
+            let (queued, _) = rb.segments();
+            let budget = queued.len().min(self.tokens);
+            let n = match sink.write(&queued[..budget]) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(n) => n,
+                Err(err)
+                    if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted) =>
+                {
+                    break
+                }
+                Err(err) => return Err(err),
+            };
+            rb.consume_head(n);
+            self.tokens -= n;
+            written += n;
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_drain_respects_rate() {
+        let mut rb = RotatingBuffer::try_new(32).unwrap();
+        rb.enqueue_partial(&[7; 32]);
+        let mut sink = Vec::new();
+        let mut throttle = Throttle::with_burst(10, 10);
+        let start = Instant::now();
+
+        assert_eq!(throttle.drain(&mut rb, &mut sink, start).unwrap(), 10);
+        assert_eq!(throttle.drain(&mut rb, &mut sink, start).unwrap(), 0);
+        // Half a second later, five more bytes have been earned.
+        let later = start + Duration::from_millis(500);
+        assert_eq!(throttle.drain(&mut rb, &mut sink, later).unwrap(), 5);
+        // Idling for a long time only refills up to the burst size.
+        let much_later = later + Duration::from_secs(60);
+        assert_eq!(throttle.drain(&mut rb, &mut sink, much_later).unwrap(), 10);
+        assert_eq!(sink.len(), 25);
+        assert_eq!(rb.len(), 7);
+    }
+
+    #[test]
+    fn test_fractional_tokens_carry() {
+        let mut rb = RotatingBuffer::try_new(8).unwrap();
+        rb.enqueue_partial(&[1; 8]);
+        let mut sink = Vec::new();
+        let start = Instant::now();
+
+        let mut throttle = Throttle::with_burst(3, 3);
+        throttle.tokens = 0;
+        throttle.drain(&mut rb, &mut sink, start).unwrap();
+        let mut now = start;
+        let mut total = 0;
+        for _ in 0..4 {
+            now += Duration::from_millis(250);
+            total += throttle.drain(&mut rb, &mut sink, now).unwrap();
+        }
+        // 0.75 bytes per step adds up to exactly three bytes over a second.
+        assert_eq!(total, 3);
+    }
+}
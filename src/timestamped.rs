@@ -0,0 +1,169 @@
+//! A [RotatingBuffer] whose contents are stamped with the time they were enqueued.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::{IncompleteEnqueue, RotBufError, RotatingBuffer};
+
+/// The [TimestampedBuffer] records when each batch of bytes was enqueued, so that data can
+/// be discarded by age rather than by count.
+///
+/// As with [crate::Throttle], the current time is passed in by the caller.  The ring
+/// records the batch boundaries itself (see [RotatingBuffer::enable_batch_tracking]),
+/// including how much of a partially dequeued batch remains; the [TimestampedBuffer] only
+/// adds their stamps.
+#[derive(Debug)]
+pub struct TimestampedBuffer {
+    ring: RotatingBuffer,
+    /// When each batch still (at least partially) in the ring was enqueued, oldest first.
+    stamps: VecDeque<Instant>,
+}
+
+impl TimestampedBuffer {
+    /// Creates a new [TimestampedBuffer] holding up to `capacity` bytes.
+    ///
+    /// # PANICS
+    ///
+    /// Panics if `capacity` is 0.  [TimestampedBuffer::try_new] reports that as an error
+    /// instead.
+    pub fn new(capacity: usize) -> Self {
+        match Self::try_new(capacity) {
+            Ok(tb) => tb,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Creates a new [TimestampedBuffer] as in [TimestampedBuffer::new], returning an [Err] with
+    /// [RotBufError::InvalidCapacity] if `capacity` is 0.
+    pub fn try_new(capacity: usize) -> Result<Self, RotBufError> {
+        let mut ring = RotatingBuffer::try_new(capacity)?;
+        ring.enable_batch_tracking();
+        Ok(Self {
+            ring,
+            stamps: VecDeque::new(),
+        })
+    }
+
+    /// Returns the underlying [RotatingBuffer], e.g. for peeking.
+    pub fn ring(&self) -> &RotatingBuffer {
+        &self.ring
+    }
+
+    /// Returns the number of bytes queued.
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+
+    /// Returns whether or not the [TimestampedBuffer] is empty.
+    pub fn is_empty(&self) -> bool {
+        self.ring.is_empty()
+    }
+
+    /// Enqueues as much of `data` as fits as one batch stamped with `now`.  Returns an
+    /// [Err] with an [IncompleteEnqueue] if only part of `data` fit.
+    pub fn push<'a>(&mut self, data: &'a [u8], now: Instant) -> Result<(), IncompleteEnqueue<'a>> {
+        let written = self.ring.enqueue_partial(data);
+        if written > 0 {
+            self.stamps.push_back(now);
+        }
+        if written < data.len() {
+            Err(IncompleteEnqueue {
+                written,
+                remainder: &data[written..],
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Dequeues the oldest byte, or returns [None] if empty.
+    pub fn dequeue(&mut self) -> Option<u8> {
+        let value = self.ring.dequeue()?;
+        self.forget_dequeued_batches();
+        Some(value)
+    }
+
+    /// Returns how long ago, as of `now`, the oldest queued byte was enqueued, or [None]
+    /// if empty.
+    pub fn oldest_age(&self, now: Instant) -> Option<Duration> {
+        self.stamps
+            .front()
+            .map(|&stamp| now.saturating_duration_since(stamp))
+    }
+
+    /// Discards every byte enqueued more than `age` before `now`, returning how many bytes
+    /// were discarded.
+    pub fn drain_older_than(&mut self, age: Duration, now: Instant) -> usize {
+        let mut drained = 0;
+        while let Some(&stamp) = self.stamps.front() {
+            if now.saturating_duration_since(stamp) <= age {
+                break;
+            }
+            let len = self.batch_lens()[0];
+            self.ring.consume_head(len);
+            self.forget_dequeued_batches();
+            drained += len;
+        }
+        drained
+    }
+
+    /// Returns the lengths of the batches in the ring, oldest first.
+    fn batch_lens(&self) -> &VecDeque<usize> {
+        self.ring
+            .batch_lens()
+            .expect("the ring always tracks its batches")
+    }
+
+    /// Drops the stamps of the batches the ring no longer holds, which are the oldest.
+    fn forget_dequeued_batches(&mut self) {
+        let dequeued = self.stamps.len() - self.batch_lens().len();
+        self.stamps.drain(..dequeued);
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_drain_older_than() {
+        let mut tb = TimestampedBuffer::new(8);
+        let start = Instant::now();
+        tb.push(&[1, 1], start).unwrap();
+        tb.push(&[2, 2, 2], start + Duration::from_secs(2)).unwrap();
+        tb.push(&[3], start + Duration::from_secs(4)).unwrap();
+
+        let now = start + Duration::from_secs(6);
+        assert_eq!(tb.oldest_age(now), Some(Duration::from_secs(6)));
+        assert_eq!(tb.drain_older_than(Duration::from_secs(5), now), 2);
+        assert_eq!(tb.oldest_age(now), Some(Duration::from_secs(4)));
+        assert_eq!(tb.drain_older_than(Duration::from_secs(5), now), 0);
+        assert_eq!(tb.drain_older_than(Duration::from_secs(1), now), 4);
+        assert!(tb.is_empty());
+        assert_eq!(tb.oldest_age(now), None);
+    }
+
+    #[test]
+    fn test_dequeue_tracks_partial_batches() {
+        let mut tb = TimestampedBuffer::new(4);
+        let start = Instant::now();
+        tb.push(&[1, 1], start).unwrap();
+        let later = start + Duration::from_secs(1);
+        let err = tb.push(&[2, 2, 2], later).unwrap_err();
+        assert_eq!(err.remainder(), &[2]);
+
+        assert_eq!(tb.dequeue(), Some(1));
+        assert_eq!(tb.dequeue(), Some(1));
+        // Only the second batch is left, so the age is measured from its stamp.
+        assert_eq!(tb.oldest_age(later), Some(Duration::ZERO));
+        assert_eq!(tb.drain_older_than(Duration::ZERO, later), 0);
+        assert_eq!(tb.len(), 2);
+
+        // Batches stamped with the same instant are drained together.
+        tb.push(&[3, 3], later).unwrap();
+        let now = later + Duration::from_secs(1);
+        assert_eq!(tb.drain_older_than(Duration::ZERO, now), 4);
+        assert_eq!(tb.oldest_age(now), None);
+    }
+}
@@ -0,0 +1,126 @@
+//! Two-phase writes that only become visible to dequeue once committed.
+
+use crate::{IncompleteEnqueue, RotatingBuffer};
+
+impl RotatingBuffer {
+    /// Begins a [WriteTransaction].  Bytes written to it are staged past the tail and only
+    /// become part of the queue when it is committed.
+    pub fn begin(&mut self) -> WriteTransaction<'_> {
+        WriteTransaction {
+            rb: self,
+            staged: 0,
+        }
+    }
+}
+
+/// A [WriteTransaction] stages bytes in the free region of a [RotatingBuffer].
+/// [WriteTransaction::commit] enqueues everything staged at once, while
+/// [WriteTransaction::abort] (or dropping the transaction) discards it, so a reader never
+/// observes a partially written message.  Created by [RotatingBuffer::begin].
+#[derive(Debug)]
+pub struct WriteTransaction<'a> {
+    rb: &'a mut RotatingBuffer,
+    /// How many bytes past the tail have been staged.
+    staged: usize,
+}
+
+impl<'a> WriteTransaction<'a> {
+    /// Returns the number of bytes staged.
+    pub fn len(&self) -> usize {
+        self.staged
+    }
+
+    /// Returns whether or not nothing has been staged.
+    pub fn is_empty(&self) -> bool {
+        self.staged == 0
+    }
+
+    /// Returns how many more bytes can be staged.
+    pub fn remaining_capacity(&self) -> usize {
+        self.rb.remaining_capacity().saturating_sub(self.staged)
+    }
+
+    /// Stages as much of `src` as fits, returning the number of bytes staged.
+    ///
+    /// If there is not enough room, the bytes that fit are still staged and an [Err] with an
+    /// [IncompleteEnqueue] borrowing the remainder is returned.  Whether to commit the
+    /// partial write or abort is left to the caller.
+    pub fn write<'s>(&mut self, src: &'s [u8]) -> Result<usize, IncompleteEnqueue<'s>> {
+        let staged = self.staged;
+        let src_len = src.len().min(self.remaining_capacity());
+        let (first, second) = self.rb.free_slots_mut();
+        // Skip over what has already been staged.
+        let (first, second) = match first.len() {
+            len if staged < len => (&mut first[staged..], second),
+            len => (&mut second[staged - len..], &mut [][..]),
+        };
+        let first_len = first.len().min(src_len);
+        first[..first_len].copy_from_slice(&src[..first_len]);
+        let second_len = second.len().min(src_len - first_len);
+        second[..second_len].copy_from_slice(&src[first_len..first_len + second_len]);
+
+        let written = first_len + second_len;
+        self.staged += written;
+        if written == src.len() {
+            Ok(written)
+        } else {
+            Err(IncompleteEnqueue {
+                written,
+                remainder: &src[written..],
+            })
+        }
+    }
+
+    /// Enqueues every staged byte at once, returning how many were enqueued.
+    pub fn commit(self) -> usize {
+        self.rb.commit_tail(self.staged);
+        self.staged
+    }
+
+    /// Discards every staged byte, leaving the queue as it was before
+    /// [RotatingBuffer::begin].
+    pub fn abort(self) {}
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_commit_across_wrap() {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        rb.enqueue_partial(&[0, 0, 0, 9]);
+        rb.consume_head(3);
+
+        let mut txn = rb.begin();
+        assert_eq!(txn.write(&[1, 2]), Ok(2));
+        assert_eq!(txn.write(&[3, 4]), Ok(2)); // Wraps around
+        assert_eq!(txn.remaining_capacity(), 1);
+        let err = txn.write(&[5, 6]).unwrap_err();
+        assert_eq!((err.written(), err.remainder()), (1, &[6][..]));
+        assert_eq!(txn.commit(), 5);
+
+        assert!(rb.at_capacity());
+        assert_eq!(rb.last_n(6).to_vec(), vec![9, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_abort_and_drop_discard() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.enqueue(7).unwrap();
+
+        let mut txn = rb.begin();
+        txn.write(&[1, 2]).unwrap();
+        txn.abort();
+        assert_eq!(rb.len(), 1);
+
+        rb.begin().write(&[3, 4, 5]).unwrap();
+        assert_eq!(rb.len(), 1);
+
+        let mut txn = rb.begin();
+        txn.write(&[6]).unwrap();
+        txn.commit();
+        assert_eq!(rb.last_n(2).to_vec(), vec![7, 6]);
+    }
+}
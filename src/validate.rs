@@ -0,0 +1,182 @@
+//! An invariant checker for the internal state of a [RotatingBuffer].
+
+use crate::RotatingBuffer;
+
+/// [ValidationReport] is produced by [RotatingBuffer::debug_validate].  It captures the raw
+/// internal state of the [RotatingBuffer] along with every invariant that was found to be
+/// violated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// The label of the ring (see [RotatingBuffer::set_label]), if any.
+    pub label: Option<&'static str>,
+    /// The capacity of the ring.
+    pub capacity: usize,
+    /// The index of the head.
+    pub head: usize,
+    /// The index of the tail.
+    pub tail: usize,
+    /// Whether the at-capacity flag was set.
+    pub at_capacity: bool,
+    /// The initialized length of the backing buffer.
+    pub buffer_len: usize,
+    /// The number of queued bytes implied by the head, tail and at-capacity flag.
+    pub len: usize,
+    /// A description of every violated invariant.  Empty if the state is valid.
+    pub violations: Vec<String>,
+}
+
+impl ValidationReport {
+    /// Returns whether every invariant held.
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl std::fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RotatingBuffer ")?;
+        if let Some(label) = self.label {
+            write!(f, "`{}` ", label)?;
+        }
+        write!(
+            f,
+            "{{ capacity: {}, head: {}, tail: {}, at_capacity: {}, buffer_len: {}, len: {} }}",
+            self.capacity, self.head, self.tail, self.at_capacity, self.buffer_len, self.len
+        )?;
+        if self.is_valid() {
+            write!(f, " is valid")
+        } else {
+            write!(f, " violates:")?;
+            for violation in &self.violations {
+                write!(f, "\n  - {}", violation)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+impl RotatingBuffer {
+    /// Exhaustively checks the internal invariants of the [RotatingBuffer] and returns a
+    /// [ValidationReport] describing the state and any violations found.
+    ///
+    /// This only reads the raw fields, so (unlike the public accessors) it will never panic
+    /// on a corrupted state, making it suitable as an oracle for fuzzers and tests.
+    pub fn debug_validate(&self) -> ValidationReport {
+        let (size, head, tail) = (self.size.get(), self.head.get(), self.tail.get());
+        let mut violations = Vec::new();
+
+        if self.poisoned {
+            violations.push("the buffer is poisoned".to_string());
+        }
+        if head >= size {
+            violations.push(format!(
+                "head ({}) is not less than the capacity ({})",
+                head, size
+            ));
+        }
+        if tail >= size {
+            violations.push(format!(
+                "tail ({}) is not less than the capacity ({})",
+                tail, size
+            ));
+        }
+        if self.at_capacity && head != tail {
+            violations.push(format!(
+                "at_capacity is set but head ({}) and tail ({}) differ",
+                head, tail
+            ));
+        }
+
+        // Out of range indices have already been reported, so reduce them into range
+        // rather than letting the arithmetic overflow.
+        let len = match (head, tail) {
+            (head, tail) if head == tail && self.at_capacity => size,
+            (head, tail) => (tail % size + size - head % size) % size,
+        };
+
+        let buffer_len = self.buffer.len();
+        if buffer_len > size {
+            violations.push(format!(
+                "backing buffer length ({}) exceeds the capacity ({})",
+                buffer_len, size
+            ));
+        }
+        if self.buffer.capacity() < size {
+            violations.push(format!(
+                "backing buffer allocation ({}) is smaller than the capacity ({})",
+                self.buffer.capacity(),
+                size
+            ));
+        }
+        // Every queued index must be initialized in the backing buffer.
+        if len > 0 && head < size && tail < size {
+            let highest = if head < tail { tail } else { size };
+            if buffer_len < highest {
+                violations.push(format!(
+                    "queued bytes extend to index {} but only {} bytes are initialized",
+                    highest - 1,
+                    buffer_len
+                ));
+            }
+        }
+
+        ValidationReport {
+            label: self.label,
+            capacity: size,
+            head,
+            tail,
+            at_capacity: self.at_capacity,
+            buffer_len,
+            len,
+            violations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_valid_through_wrapping() {
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
+        for value in 0..10 {
+            rb.enqueue(value).unwrap();
+            assert!(rb.debug_validate().is_valid());
+            if value % 2 == 0 {
+                rb.dequeue();
+                assert!(rb.debug_validate().is_valid());
+            }
+            if rb.at_capacity() {
+                assert_eq!(rb.debug_validate().len, 3);
+                rb.dequeue();
+                rb.dequeue();
+            }
+        }
+    }
+
+    #[test]
+    fn test_reports_violations() {
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
+        rb.enqueue(1).unwrap();
+        rb.at_capacity = true;
+        rb.head = crate::index::Index::unchecked(5);
+        let report = rb.debug_validate();
+        assert!(!report.is_valid());
+        assert_eq!(report.violations.len(), 2);
+        assert!(report.to_string().contains("head (5)"));
+    }
+
+    #[test]
+    fn test_label() {
+        let mut rb = RotatingBuffer::try_new(3).unwrap();
+        assert!(rb.debug_validate().to_string().starts_with("RotatingBuffer {"));
+        rb.set_label("uplink-tx");
+        assert_eq!(rb.label(), Some("uplink-tx"));
+        let report = rb.debug_validate();
+        assert_eq!(report.label, Some("uplink-tx"));
+        assert!(report.to_string().starts_with("RotatingBuffer `uplink-tx` {"));
+        assert!(format!("{:?}", rb).contains("uplink-tx"));
+    }
+}
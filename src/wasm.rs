@@ -0,0 +1,91 @@
+//! Interop between the [RotatingBuffer] and JavaScript `Uint8Array`s, available with
+//! the `wasm` feature.
+
+use js_sys::Uint8Array;
+
+use crate::RotatingBuffer;
+
+impl RotatingBuffer {
+    /// Enqueues as many bytes from `src` as will fit, copying straight from the JS heap
+    /// into the free region of the buffer, as [RotatingBuffer::enqueue_slice] does
+    /// (including its [crate::OverflowPolicy]).  Returns the number of bytes enqueued,
+    /// which is less than `src.length()` if the [RotatingBuffer] ran out of capacity, and
+    /// 0 if it is poisoned.
+    pub fn enqueue_from_js(&mut self, src: &Uint8Array) -> usize {
+        self.enqueue_slice_with(src.length() as usize, |offset, dst| {
+            src.subarray(offset as u32, (offset + dst.len()) as u32)
+                .copy_to(dst)
+        })
+    }
+
+    /// Dequeues up to `n` bytes into a newly allocated `Uint8Array`.  The returned array
+    /// is shorter than `n` if fewer bytes were queued.
+    pub fn dequeue_to_js(&mut self, n: usize) -> Uint8Array {
+        let n = n.min(self.len());
+        let out = Uint8Array::new_with_length(n as u32);
+        let (first, second) = self.segments();
+
+        let first_len = first.len().min(n);
+        out.subarray(0, first_len as u32)
+            .copy_from(&first[..first_len]);
+        out.subarray(first_len as u32, n as u32)
+            .copy_from(&second[..n - first_len]);
+
+        self.consume_head(n);
+        out
+    }
+
+    /// Returns `Uint8Array` views directly over the queued bytes, in queue order.  The
+    /// second view is empty unless the queue wraps around the end of the buffer.
+    ///
+    /// # Safety
+    ///
+    /// The views alias the WebAssembly linear memory backing the [RotatingBuffer].  They
+    /// must not be used after the buffer is mutated or dropped, or after anything causes
+    /// the WebAssembly memory to grow (which detaches every view).
+    pub unsafe fn js_views(&self) -> (Uint8Array, Uint8Array) {
+        let (first, second) = self.segments();
+        (Uint8Array::view(first), Uint8Array::view(second))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::OverflowPolicy;
+
+    /// Enqueues `len` bytes numbered from 1 the way [RotatingBuffer::enqueue_from_js] does,
+    /// recording the `(offset, len)` of every copy out of the source.
+    fn enqueue_numbered(rb: &mut RotatingBuffer, len: usize) -> (usize, Vec<(usize, usize)>) {
+        let mut copies = Vec::new();
+        let written = rb.enqueue_slice_with(len, |offset, dst| {
+            copies.push((offset, dst.len()));
+            for (i, slot) in dst.iter_mut().enumerate() {
+                *slot = (offset + i + 1) as u8;
+            }
+        });
+        (written, copies)
+    }
+
+    #[test]
+    fn test_copies_split_across_wrap() {
+        let mut rb = RotatingBuffer::try_new(6).unwrap();
+        rb.enqueue_partial(&[0; 4]);
+        rb.consume_head(4);
+        assert_eq!(enqueue_numbered(&mut rb, 8), (6, vec![(0, 2), (2, 4)]));
+        assert_eq!(rb.last_n(6).to_vec(), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(enqueue_numbered(&mut rb, 1), (0, vec![]));
+
+        rb.set_overflow_policy(OverflowPolicy::DropOldest);
+        assert_eq!(enqueue_numbered(&mut rb, 2), (2, vec![(0, 2)]));
+        assert_eq!(rb.last_n(6).to_vec(), vec![3, 4, 5, 6, 1, 2]);
+    }
+
+    #[test]
+    fn test_poisoned_enqueues_nothing() {
+        let mut rb = RotatingBuffer::try_new(4).unwrap();
+        rb.poisoned = true;
+        assert_eq!(enqueue_numbered(&mut rb, 2), (0, vec![]));
+    }
+}
@@ -0,0 +1,127 @@
+//! Detection of consumers that stopped draining a [RotatingBuffer].
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::RotatingBuffer;
+
+/// The [Watchdog] notices when the bytes at the head of a [RotatingBuffer] have not been
+/// dequeued for longer than a threshold, e.g. because the downstream writer is wedged.
+///
+/// Call [Watchdog::observe] regularly (such as after every enqueue, or on a timer).  The
+/// head counts as progressing whenever [RotatingBuffer::dequeue_offset] moves, and the
+/// clock is reset whenever the ring is empty, as there is nothing to consume.  The
+/// [Watchdog::on_stall] callback fires once per stall, when it first passes the threshold.
+pub struct Watchdog {
+    threshold: Duration,
+    /// The dequeue offset last observed, and since when it has not moved while data was
+    /// queued.
+    waiting: Option<(u64, Instant)>,
+    /// Whether the current stall was already reported to the callback.
+    reported: bool,
+    on_stall: Option<Box<dyn FnMut(Duration) + Send>>,
+}
+
+impl fmt::Debug for Watchdog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watchdog")
+            .field("threshold", &self.threshold)
+            .field("waiting", &self.waiting)
+            .field("reported", &self.reported)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Watchdog {
+    /// Creates a new [Watchdog] reporting a stall once the head has not moved for
+    /// `threshold`.
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            waiting: None,
+            reported: false,
+            on_stall: None,
+        }
+    }
+
+    /// Sets a callback invoked with how long the head has been stuck, once per stall, when
+    /// it first passes the threshold.
+    pub fn on_stall(mut self, callback: impl FnMut(Duration) + Send + 'static) -> Self {
+        self.on_stall = Some(Box::new(callback));
+        self
+    }
+
+    /// Returns the threshold past which the head counts as stalled.
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// Records the state of `rb` at `now`, and returns how long its head has been stalled
+    /// for, as [Watchdog::stalled_for] does.
+    pub fn observe(&mut self, rb: &RotatingBuffer, now: Instant) -> Option<Duration> {
+        let offset = rb.dequeue_offset();
+        let stuck = matches!(self.waiting, Some((last, _)) if last == offset);
+        if rb.is_empty() || !stuck {
+            self.waiting = (!rb.is_empty()).then_some((offset, now));
+            self.reported = false;
+        }
+        let stalled = self.stalled_for(now)?;
+        if !self.reported {
+            self.reported = true;
+            if let Some(callback) = &mut self.on_stall {
+                callback(stalled);
+            }
+        }
+        Some(stalled)
+    }
+
+    /// Returns how long the head had been stuck at `now`, if that is at least the
+    /// threshold, based on the last [Watchdog::observe].  Returns [None] if the head is not
+    /// stalled.
+    pub fn stalled_for(&self, now: Instant) -> Option<Duration> {
+        let (_, since) = self.waiting?;
+        let waited = now.saturating_duration_since(since);
+        (waited >= self.threshold).then_some(waited)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_reports_stalls_once() {
+        let stalls = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&stalls);
+        let mut watchdog = Watchdog::new(Duration::from_secs(5))
+            .on_stall(move |stalled| log.lock().unwrap().push(stalled));
+        let mut rb = RotatingBuffer::try_new(8).unwrap();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        // An empty ring never stalls.
+        assert_eq!(watchdog.observe(&rb, at(0)), None);
+        assert_eq!(watchdog.observe(&rb, at(10)), None);
+
+        rb.enqueue_partial(&[1, 2, 3]);
+        assert_eq!(watchdog.observe(&rb, at(10)), None);
+        assert_eq!(watchdog.observe(&rb, at(14)), None);
+        assert_eq!(watchdog.observe(&rb, at(15)), Some(Duration::from_secs(5)));
+        assert_eq!(watchdog.observe(&rb, at(17)), Some(Duration::from_secs(7)));
+        assert_eq!(watchdog.stalled_for(at(20)), Some(Duration::from_secs(10)));
+
+        // Progress restarts the clock, and a later stall is reported again.
+        rb.dequeue();
+        assert_eq!(watchdog.observe(&rb, at(20)), None);
+        rb.enqueue_partial(&[4]);
+        assert_eq!(watchdog.observe(&rb, at(24)), None);
+        assert_eq!(watchdog.observe(&rb, at(26)), Some(Duration::from_secs(6)));
+
+        assert_eq!(
+            *stalls.lock().unwrap(),
+            vec![Duration::from_secs(5), Duration::from_secs(6)]
+        );
+    }
+}